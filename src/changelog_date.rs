@@ -0,0 +1,194 @@
+use crate::config::{ChangelogConfig, ChangelogTimezone};
+use crate::git::commit_timestamp;
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::*;
+use eyre::{Result, WrapErr};
+use std::env;
+
+static DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d"; // e.g. 2020-10-04
+static SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Picks the dates of changelog entries. Existing releases are dated with the time of their
+/// tagged commit. For a new release an explicit date wins, then `SOURCE_DATE_EPOCH`, then today.
+#[derive(Debug, Clone)]
+pub struct ChangelogDates {
+    /// Formatted as is, without converting it to the timezone
+    date: Option<DateTime<FixedOffset>>,
+    /// Seconds since the Unix epoch
+    source_date_epoch: Option<i64>,
+    format: String,
+    timezone: ChangelogTimezone,
+}
+
+impl Default for ChangelogDates {
+    fn default() -> Self {
+        ChangelogDates {
+            date: None,
+            source_date_epoch: None,
+            format: DEFAULT_DATE_FORMAT.to_string(),
+            timezone: ChangelogTimezone::Local,
+        }
+    }
+}
+
+impl ChangelogDates {
+    /// The date settings of the config, with `SOURCE_DATE_EPOCH` from the environment.
+    pub fn from_config(config: &ChangelogConfig) -> Result<Self> {
+        let source_date_epoch = match env::var(SOURCE_DATE_EPOCH) {
+            Ok(epoch) => Some(epoch.trim().parse().wrap_err_with(|| {
+                format!(
+                    "{} must be a Unix timestamp, got {}",
+                    SOURCE_DATE_EPOCH, epoch
+                )
+            })?),
+            Err(_) => None,
+        };
+        ChangelogDates::new(config, source_date_epoch)
+    }
+
+    fn new(config: &ChangelogConfig, source_date_epoch: Option<i64>) -> Result<Self> {
+        let format = config
+            .date_format
+            .to_owned()
+            .unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return Err(eyre!("Invalid date format '{}'", format));
+        }
+
+        Ok(ChangelogDates {
+            date: config.date.as_deref().map(parse_date).transpose()?,
+            source_date_epoch,
+            format,
+            timezone: config.timezone,
+        })
+    }
+
+    /// The date of the release being made, today unless set otherwise.
+    pub fn release_date(&self) -> Result<String> {
+        if let Some(date) = self.date {
+            return Ok(date.format(&self.format).to_string());
+        }
+
+        let timestamp = self
+            .source_date_epoch
+            .unwrap_or_else(|| Utc::now().timestamp());
+        self.format_timestamp(timestamp)
+    }
+
+    /// The date of an existing release, the time of its tagged commit.
+    pub fn tag_date(&self, tag: &str) -> Result<String> {
+        self.format_timestamp(commit_timestamp(tag)?)
+    }
+
+    fn format_timestamp(&self, timestamp: i64) -> Result<String> {
+        let date = Utc
+            .timestamp_opt(timestamp, 0)
+            .single()
+            .ok_or_else(|| eyre!("Invalid timestamp {}", timestamp))?;
+
+        Ok(match self.timezone {
+            ChangelogTimezone::Utc => date.format(&self.format).to_string(),
+            ChangelogTimezone::Local => date.with_timezone(&Local).format(&self.format).to_string(),
+        })
+    }
+}
+
+/// Parses a date like `2020-10-04` or an RFC 3339 timestamp.
+fn parse_date(date: &str) -> Result<DateTime<FixedOffset>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date);
+    }
+
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").wrap_err_with(|| {
+        format!(
+            "Invalid date '{}', expected e.g. 2020-10-04 or 2020-10-04T12:00:00+02:00",
+            date
+        )
+    })?;
+    Ok(FixedOffset::east(0).from_utc_datetime(&day.and_hms(0, 0, 0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(date: Option<&str>, timezone: ChangelogTimezone) -> ChangelogConfig {
+        ChangelogConfig {
+            date: date.map(String::from),
+            timezone,
+            ..ChangelogConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_explicit_date() {
+        let dates =
+            ChangelogDates::new(&config(Some("2020-10-04"), ChangelogTimezone::Utc), None).unwrap();
+        assert_eq!(dates.release_date().unwrap(), "2020-10-04");
+
+        let dates = ChangelogDates::new(
+            &config(Some("2020-10-04T23:30:00-02:00"), ChangelogTimezone::Utc),
+            Some(0),
+        )
+        .unwrap();
+        assert_eq!(dates.release_date().unwrap(), "2020-10-04");
+
+        assert!(
+            ChangelogDates::new(&config(Some("04/10/2020"), ChangelogTimezone::Utc), None).is_err()
+        );
+    }
+
+    #[test]
+    fn test_source_date_epoch() {
+        // 2020-10-04T23:30:00Z
+        let dates =
+            ChangelogDates::new(&config(None, ChangelogTimezone::Utc), Some(1601854200)).unwrap();
+        assert_eq!(dates.release_date().unwrap(), "2020-10-04");
+
+        let config = ChangelogConfig {
+            date_format: Some("%B %-d, %Y %H:%M".to_string()),
+            timezone: ChangelogTimezone::Utc,
+            ..ChangelogConfig::default()
+        };
+        let dates = ChangelogDates::new(&config, Some(1601854200)).unwrap();
+        assert_eq!(dates.release_date().unwrap(), "October 4, 2020 23:30");
+    }
+
+    #[test]
+    fn test_release_date() {
+        let dates = ChangelogDates::new(&config(None, ChangelogTimezone::Utc), None).unwrap();
+        assert_eq!(
+            dates.release_date().unwrap(),
+            Utc::now().format(DEFAULT_DATE_FORMAT).to_string()
+        );
+    }
+
+    #[test]
+    fn test_tag_date() {
+        let dates = ChangelogDates::new(&config(None, ChangelogTimezone::Local), None).unwrap();
+        assert!(dates.tag_date("HEAD").is_ok());
+        assert!(dates.tag_date("no-such-ref").is_err());
+
+        // Existing releases keep their date
+        let dates = ChangelogDates::new(
+            &config(Some("2020-10-04"), ChangelogTimezone::Utc),
+            Some(1601854200),
+        )
+        .unwrap();
+        let head = Utc
+            .timestamp_opt(commit_timestamp("HEAD").unwrap(), 0)
+            .unwrap()
+            .format(DEFAULT_DATE_FORMAT)
+            .to_string();
+        assert_eq!(dates.tag_date("HEAD").unwrap(), head);
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let config = ChangelogConfig {
+            date_format: Some("%Y-%Q".to_string()),
+            ..ChangelogConfig::default()
+        };
+        assert!(ChangelogDates::new(&config, None).is_err());
+    }
+}
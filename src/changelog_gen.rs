@@ -1,67 +1,245 @@
+use crate::changelog_date::ChangelogDates;
+use crate::changelog_template::{
+    ChangelogTemplates, TemplateContext, ENTRY_TEMPLATE, RELEASE_TEMPLATE, SUMMARY_TEMPLATE,
+};
 use crate::commit::Commit;
-use crate::git::{commits_in_log, first_commit, last_tag};
-use chrono::prelude::*;
-use eyre::Result;
-use semver::Version;
+use crate::commit_filter::{CommitFilter, DroppedCommit};
+use crate::config::ChangelogFormat;
+use crate::git::{commits_in_log, first_commit, tag_exists};
+use crate::github::RepositoryLinks;
+use crate::keep_a_changelog;
+use eyre::{Result, WrapErr};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::fs;
+use std::path::Path;
 
 static CHANGELOG_FILE_PATH: &str = "CHANGELOG.md";
 static CHANGELOG_HEADER: &str = "# CHANGELOG\n\n";
+/// The heading of a changelog entry
+pub static ENTRY_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^## ").unwrap());
+
+/// The version a changelog is generated for and the release before it.
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub tag: String,
+    /// `None` for the first release
+    pub previous_version: Option<String>,
+    pub previous_tag: Option<String>,
+}
+
+/// The changes of a release, rendered with the changelog templates.
+pub struct ChangelogEntry {
+    pub release: ReleaseInfo,
+    pub date: String,
+    pub commits: Vec<Commit>,
+    /// Commits of the release left out of the changelog
+    pub dropped: Vec<DroppedCommit>,
+}
 
-pub struct ChangelogGenerator {}
+pub struct ChangelogGenerator {
+    changelog_path: String,
+    /// Only commits touching these paths are included, all commits when empty
+    paths: Vec<String>,
+    /// Workspace packages bumped along with this release, e.g. `pkg-a@1.4.0`
+    updated_dependencies: Vec<String>,
+    /// Leaves out release commits and the ones excluded in the config
+    filter: CommitFilter,
+    format: ChangelogFormat,
+    dates: ChangelogDates,
+    templates: ChangelogTemplates,
+    /// Links to the repository, changelogs are plain text without them
+    links: Option<RepositoryLinks>,
+}
 
 impl ChangelogGenerator {
     pub fn new() -> Self {
-        ChangelogGenerator {}
+        ChangelogGenerator {
+            changelog_path: CHANGELOG_FILE_PATH.to_string(),
+            paths: vec![],
+            updated_dependencies: vec![],
+            filter: CommitFilter::default(),
+            format: ChangelogFormat::Default,
+            dates: ChangelogDates::default(),
+            templates: ChangelogTemplates::new(),
+            links: None,
+        }
+    }
+
+    /// Creates a generator for the CHANGELOG.md in the package directory,
+    /// only including commits that touch the package.
+    pub fn for_package(path: &str) -> Self {
+        let changelog_path = Path::new(path).join(CHANGELOG_FILE_PATH);
+        ChangelogGenerator {
+            changelog_path: changelog_path.to_string_lossy().to_string(),
+            paths: vec![path.to_owned()],
+            updated_dependencies: vec![],
+            filter: CommitFilter::default(),
+            format: ChangelogFormat::Default,
+            dates: ChangelogDates::default(),
+            templates: ChangelogTemplates::new(),
+            links: None,
+        }
+    }
+
+    /// Lists workspace packages whose new versions were picked up by this release.
+    pub fn set_updated_dependencies(&mut self, updated_dependencies: Vec<String>) {
+        self.updated_dependencies = updated_dependencies;
+    }
+
+    pub fn set_filter(&mut self, filter: CommitFilter) {
+        self.filter = filter;
+    }
+
+    pub fn set_format(&mut self, format: ChangelogFormat) {
+        self.format = format;
+    }
+
+    pub fn set_dates(&mut self, dates: ChangelogDates) {
+        self.dates = dates;
     }
 
-    // Generates a changelog between the current version and the new version
+    pub fn set_templates(&mut self, templates: ChangelogTemplates) {
+        self.templates = templates;
+    }
+
+    pub fn set_links(&mut self, links: RepositoryLinks) {
+        self.links = Some(links);
+    }
+
+    pub fn changelog_path(&self) -> &str {
+        &self.changelog_path
+    }
+
+    // Generates a changelog between the previous tag and the new tag
     pub async fn generate_changelog(
         &self,
         main_branch: &str,
-        new_ver: &Version,
+        release: ReleaseInfo,
+    ) -> Result<ChangelogEntry> {
+        info!("📎 Generating a changelog for {}", release.tag);
+
+        let entry = self.changelog_entry(main_branch, release)?;
+        self.update_changelog(&entry)?;
+
+        Ok(entry)
+    }
+
+    /// Collects the changes of a release up to `to` without touching the changelog file.
+    /// Releases that are already tagged keep the date of their tag.
+    pub fn changelog_entry(&self, to: &str, release: ReleaseInfo) -> Result<ChangelogEntry> {
+        let log = self.log_since(to, release.previous_tag.as_deref())?;
+        let (commits, dropped) = self.filter.apply(log)?;
+        let date = if tag_exists(&release.tag)? {
+            self.dates.tag_date(&release.tag)?
+        } else {
+            self.dates.release_date()?
+        };
+        Ok(ChangelogEntry {
+            release,
+            date,
+            commits,
+            dropped,
+        })
+    }
+
+    /// Returns the commits listed in the changelog since the previous tag.
+    pub fn commits_since(
+        &self,
+        main_branch: &str,
+        previous_tag: Option<&str>,
     ) -> Result<Vec<Commit>> {
-        info!("📎 Generating a changelog for v{}", new_ver);
+        let (commits, _) = self
+            .filter
+            .apply(self.log_since(main_branch, previous_tag)?)?;
+        Ok(commits)
+    }
 
-        // Find last tag
-        let commits = match last_tag()? {
+    /// Returns all commits on the branch since the previous tag,
+    /// or since the first commit when nothing has been tagged yet.
+    fn log_since(&self, main_branch: &str, previous_tag: Option<&str>) -> Result<Vec<Commit>> {
+        let range = match previous_tag {
             Some(tag) => {
-                debug!("📝 Last version is v{}", tag);
-                // Get commits from last tag
-                let range = format!("{}..{}", tag, main_branch);
-                commits_in_log(&[range])?
+                debug!("📝 Last version is {}", tag);
+                format!("{}..{}", tag, main_branch)
             }
             None => {
                 // No tag, get the first commit instead
                 let first_commit = first_commit()?;
-                let range = format!("{}..{}", first_commit, main_branch);
-                commits_in_log(&[range])?
+                format!("{}..{}", first_commit, main_branch)
             }
         };
 
-        self.update_changelog(&commits, new_ver)?;
+        let mut log_args = vec![range];
+        if !self.paths.is_empty() {
+            log_args.push("--".to_string());
+            log_args.extend(self.paths.iter().cloned());
+        }
+        commits_in_log(&log_args)
+    }
 
-        Ok(commits)
+    pub fn update_changelog(&self, entry: &ChangelogEntry) -> Result<bool> {
+        let contents = self.markdown_changelog(entry)?;
+        match self.format {
+            ChangelogFormat::Default => self.write_changelog(&entry.release.tag, contents),
+            ChangelogFormat::KeepAChangelog => {
+                debug!(
+                    "Release {} in {}",
+                    entry.release.version, self.changelog_path
+                );
+
+                let changelog_file_contents = self.read_changelog_contents()?;
+                let updated_contents =
+                    self.promote_unreleased(&changelog_file_contents, entry, &contents)?;
+                let write_res = fs::write(&self.changelog_path, updated_contents)
+                    .map_err(|e| eyre!(e.to_string()));
+
+                Ok(write_res.is_ok())
+            }
+        }
     }
 
-    pub fn update_changelog(&self, commits: &[Commit], version: &Version) -> Result<bool> {
-        let entry = self.markdown_changelog(commits, Some(version));
-        self.write_changelog(version, entry)
+    /// Turns the Unreleased section of a Keep a Changelog file into the new release
+    /// and updates the link definitions.
+    fn promote_unreleased(
+        &self,
+        contents: &str,
+        entry: &ChangelogEntry,
+        new_entry: &str,
+    ) -> Result<String> {
+        let release = &entry.release;
+        let contents = keep_a_changelog::promote_unreleased(contents, &release.version, new_entry)
+            .wrap_err_with(|| format!("Could not update {}", self.changelog_path))?;
+
+        let links = match &self.links {
+            Some(links) => links,
+            None => return Ok(contents),
+        };
+        let release_url = match &release.previous_tag {
+            Some(previous_tag) => links.compare(previous_tag, &release.tag),
+            None => links.release(&release.tag),
+        };
+        Ok(keep_a_changelog::update_link_definitions(
+            &contents,
+            &release.version,
+            &release_url,
+            &links.compare(&release.tag, "HEAD"),
+        ))
     }
 
     /// Inserts the new changelog entry below the main header to
     /// maintain ascending order by date.
-    pub fn insert_entry(
-        &self,
-        contents: String,
-        version: &Version,
-        new_entry: String,
-    ) -> Result<String> {
-        if contents.contains(&format!("## v{}", version)) {
-            return Err(eyre!("Version entry already in CHANGELOG.md"));
+    pub fn insert_entry(&self, contents: String, tag: &str, new_entry: String) -> Result<String> {
+        if contents.contains(&format!("## {} ", tag)) || contents.contains(&format!("## [{}]", tag))
+        {
+            return Err(eyre!("Version entry already in {}", self.changelog_path));
         }
         if !contents.starts_with("# CHANGELOG") {
-            return Err(eyre!("CHANGELOG.md must start with '# CHANGELOG'"));
+            return Err(eyre!(
+                "{} must start with '# CHANGELOG'",
+                self.changelog_path
+            ));
         }
         let entry_to_insert = format!("{}{}\n", CHANGELOG_HEADER, new_entry);
         let new_contents = contents.replace(CHANGELOG_HEADER, &entry_to_insert);
@@ -69,210 +247,392 @@ impl ChangelogGenerator {
         Ok(new_contents)
     }
 
+    /// Writes the changelog from scratch with an entry for each of the releases,
+    /// oldest first, keeping what comes before the first entry.
+    pub fn rebuild_changelog(&self, releases: &[ReleaseInfo]) -> Result<()> {
+        let mut entries = vec![];
+        for release in releases {
+            debug!("📎 Generating a changelog for {}", release.tag);
+            let entry = self.changelog_entry(&release.tag, release.to_owned())?;
+            entries.push(self.markdown_changelog(&entry)?);
+        }
+        entries.reverse();
+
+        let contents = self.read_changelog_contents()?;
+        let mut new_contents = match self.format {
+            ChangelogFormat::Default => {
+                let first_entry = ENTRY_HEADING.find(&contents);
+                contents[..first_entry.map(|m| m.start()).unwrap_or(contents.len())].to_owned()
+            }
+            ChangelogFormat::KeepAChangelog => keep_a_changelog::preamble(&contents),
+        };
+        for entry in entries {
+            new_contents = format!("{}\n\n{}", new_contents.trim_end(), entry);
+        }
+
+        if let (ChangelogFormat::KeepAChangelog, Some(links)) = (self.format, &self.links) {
+            for release in releases {
+                let release_url = match &release.previous_tag {
+                    Some(previous_tag) => links.compare(previous_tag, &release.tag),
+                    None => links.release(&release.tag),
+                };
+                new_contents = keep_a_changelog::update_link_definitions(
+                    &new_contents,
+                    &release.version,
+                    &release_url,
+                    &links.compare(&release.tag, "HEAD"),
+                );
+            }
+        }
+
+        fs::write(&self.changelog_path, new_contents)?;
+        Ok(())
+    }
+
     pub fn read_changelog_contents(&self) -> Result<String> {
+        let header = match self.format {
+            ChangelogFormat::Default => CHANGELOG_HEADER,
+            ChangelogFormat::KeepAChangelog => keep_a_changelog::HEADER,
+        };
+
         // File does probably not exist when it can not be read
         // so create a file with the header
-        if fs::read_to_string(CHANGELOG_FILE_PATH).is_err() {
-            fs::write(CHANGELOG_FILE_PATH, CHANGELOG_HEADER)?;
+        if fs::read_to_string(&self.changelog_path).is_err() {
+            fs::write(&self.changelog_path, header)?;
         }
 
-        let contents = fs::read_to_string(CHANGELOG_FILE_PATH)?;
+        let contents = fs::read_to_string(&self.changelog_path)?;
         if contents.is_empty() {
-            fs::write(CHANGELOG_FILE_PATH, CHANGELOG_HEADER)?;
+            fs::write(&self.changelog_path, header)?;
         }
 
-        let contents = fs::read_to_string(CHANGELOG_FILE_PATH)?;
+        let contents = fs::read_to_string(&self.changelog_path)?;
         Ok(contents)
     }
 
     /// Ensures the changelog is valid and injects the new changelog entry
     /// to the top of the file, below the header.
-    pub fn write_changelog(&self, version: &Version, new_entry: String) -> Result<bool> {
-        debug!("Add {} to CHANGELOG.md", version);
+    pub fn write_changelog(&self, tag: &str, new_entry: String) -> Result<bool> {
+        debug!("Add {} to {}", tag, self.changelog_path);
 
         let changelog_file_contents = self.read_changelog_contents()?;
-        let updated_contents = self.insert_entry(changelog_file_contents, version, new_entry)?;
+        let updated_contents = self.insert_entry(changelog_file_contents, tag, new_entry)?;
         let write_res =
-            fs::write(CHANGELOG_FILE_PATH, updated_contents).map_err(|e| eyre!(e.to_string()));
+            fs::write(&self.changelog_path, updated_contents).map_err(|e| eyre!(e.to_string()));
 
         Ok(write_res.is_ok())
     }
 
-    /// Creates a changelog in markdown format.
-    pub fn markdown_changelog(&self, commits: &[Commit], version: Option<&Version>) -> String {
-        let current_date = Local::now().date().format("%Y-%m-%d").to_string(); // e.g. 2020-10-04
-        let version_header = match version {
-            Some(version) => format!("## v{} ({})", version, current_date),
-            None => format!("## {}", current_date),
+    /// The values the changelog templates are rendered with.
+    fn template_context<'a>(&'a self, entry: &'a ChangelogEntry) -> TemplateContext<'a> {
+        let release = &entry.release;
+        let compare_url = match (&self.links, &release.previous_tag) {
+            (Some(links), Some(previous_tag)) => Some(links.compare(previous_tag, &release.tag)),
+            _ => None,
         };
 
-        let change_list = if commits.is_empty() {
-            "No commits since last version\n".to_string()
-        } else {
-            commits
-                .iter()
-                .map(|commit| format!("- {}\n", commit.compact()))
-                .collect::<String>()
-        };
+        TemplateContext {
+            version: &release.version,
+            tag: &release.tag,
+            previous_version: release.previous_version.as_deref(),
+            previous_tag: release.previous_tag.as_deref(),
+            date: &entry.date,
+            commits: vec![],
+            groups: vec![],
+            sections: vec![],
+            breaking_changes: vec![],
+            authors: vec![],
+            updated_dependencies: &self.updated_dependencies,
+            repository_url: self.links.as_ref().map(RepositoryLinks::url),
+            compare_url,
+        }
+        .commits(&entry.commits, self.links.as_ref())
+    }
 
-        format!("{}\n\n{}", version_header, change_list)
+    /// Creates the CHANGELOG.md entry in markdown format.
+    pub fn markdown_changelog(&self, entry: &ChangelogEntry) -> Result<String> {
+        self.templates
+            .render(ENTRY_TEMPLATE, &self.template_context(entry))
     }
 
-    /// Creates a compact output of commits for the CLI to print in the terminal.
-    pub fn compact_changelog(&self, commits: &[Commit]) -> String {
-        // This fn can be extended to display stats and other things
-        if commits.is_empty() {
-            return "No commits since last version".to_string();
-        }
+    /// Creates the body of the GitHub release.
+    pub fn release_body(&self, entry: &ChangelogEntry) -> Result<String> {
+        self.templates
+            .render(RELEASE_TEMPLATE, &self.template_context(entry))
+    }
+
+    /// Creates the template values of the entry as JSON.
+    pub fn json_changelog(&self, entry: &ChangelogEntry) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.template_context(entry))?)
+    }
 
-        commits
-            .iter()
-            .map(|commit| format!(" - {}\n", commit.compact()))
-            .collect::<String>()
+    /// Creates a compact output of commits for the CLI to print in the terminal.
+    pub fn compact_changelog(&self, entry: &ChangelogEntry) -> Result<String> {
+        self.templates
+            .render(SUMMARY_TEMPLATE, &self.template_context(entry))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commit;
 
     #[test]
     fn test_update_changelog() {
         //
     }
 
+    #[test]
+    fn test_for_package() {
+        let change_gen = ChangelogGenerator::for_package("packages/pkg-a");
+
+        assert_eq!(change_gen.changelog_path(), "packages/pkg-a/CHANGELOG.md");
+        assert_eq!(change_gen.paths, vec!["packages/pkg-a"]);
+    }
+
     #[test]
     fn test_insert_entry_empty() {
-        let change_gen = ChangelogGenerator {};
+        let change_gen = ChangelogGenerator::new();
 
         let log = "".to_owned();
-        let version = &Version::parse("0.1.2").unwrap();
+        let tag = "v0.1.2";
         let new_entry = "## v0.1.2 (2020-10-05)\n\n- change 1\nchange 2".to_string();
 
-        let res = change_gen.insert_entry(log, version, new_entry.to_string());
+        let res = change_gen.insert_entry(log, tag, new_entry.to_string());
         assert!(res.is_err());
     }
 
     #[test]
     fn test_insert_entry_already_exists() {
-        let change_gen = ChangelogGenerator {};
+        let change_gen = ChangelogGenerator::new();
 
         let log = "# CHANGELOG\n\n## v0.1.2 (2020-10-04)".to_owned();
-        let version = &Version::parse("0.1.2").unwrap();
+        let tag = "v0.1.2";
         let new_entry = "## v0.1.2 (2020-10-05)\n\n- change 1\nchange 2".to_string();
 
-        let res = change_gen.insert_entry(log, version, new_entry.to_string());
+        let res = change_gen.insert_entry(log, tag, new_entry.to_string());
+        assert!(res.is_err());
+
+        let linked = "# CHANGELOG\n\n## [v0.1.2](https://github.com/o/r/compare/v0.1.1...v0.1.2)";
+        let res = change_gen.insert_entry(linked.to_owned(), tag, new_entry);
         assert!(res.is_err());
     }
 
     #[test]
     fn test_insert_entry_incorrect_header() {
-        let change_gen = ChangelogGenerator {};
+        let change_gen = ChangelogGenerator::new();
 
         let log = "# RELEASES".to_owned();
-        let version = &Version::parse("0.1.2").unwrap();
+        let tag = "v0.1.2";
         let new_entry = "## v0.1.2 (2020-10-05)\n\n- change 1\nchange 2".to_string();
 
-        let res = change_gen.insert_entry(log, version, new_entry.to_string());
+        let res = change_gen.insert_entry(log, tag, new_entry.to_string());
         assert!(res.is_err());
     }
 
     #[test]
     fn test_insert_entry_just_header() {
-        let change_gen = ChangelogGenerator {};
+        let change_gen = ChangelogGenerator::new();
 
         let log = CHANGELOG_HEADER.to_owned();
-        let version = &Version::parse("0.1.2").unwrap();
+        let tag = "v0.1.2";
         let new_entry = "## v0.1.2 (2020-10-05)\n\n- change 1\nchange 2".to_string();
 
         let res = change_gen
-            .insert_entry(log, version, new_entry.to_string())
+            .insert_entry(log, tag, new_entry.to_string())
             .unwrap();
         let expected = format!("{}{}\n", CHANGELOG_HEADER, new_entry);
         assert_eq!(res, expected);
     }
 
-    #[test]
-    fn test_markdown_changelog() {
-        let change_gen = ChangelogGenerator {};
-        let ver = &Version::parse("1.2.3").unwrap();
+    fn entry(commits: Vec<Commit>) -> ChangelogEntry {
+        ChangelogEntry {
+            release: ReleaseInfo {
+                version: "1.2.3".to_string(),
+                tag: "v1.2.3".to_string(),
+                previous_version: Some("1.2.2".to_string()),
+                previous_tag: Some("v1.2.2".to_string()),
+            },
+            date: "2020-10-04".to_string(),
+            commits,
+            dropped: vec![],
+        }
+    }
 
-        let user = commit::User {
-            name: "name".to_string(),
-            email: "email".to_string(),
-            date: "date".to_string(),
-        };
+    fn commits() -> Vec<Commit> {
+        vec![
+            Commit::for_test("2222222222", "second", "name"),
+            Commit::for_test("1111111111", "first", "name"),
+        ]
+    }
 
-        let non_empty: Vec<Commit> = vec![
-            commit::Commit {
-                commit: "commit2".to_string(),
-                abbreviated_commit: "abb_commit2".to_string(),
-                refs: "refs2".to_string(),
-                commit_notes: "commit_notes".to_string(),
-                subject: "second".to_string(),
-                sanitized_subject_line: "sanitized-subject-line".to_string(),
-                author: user.to_owned(),
-                committer: user.to_owned(),
-            },
-            commit::Commit {
-                commit: "commit1".to_string(),
-                abbreviated_commit: "abb_commit1".to_string(),
-                refs: "refs1".to_string(),
-                commit_notes: "commit_notes".to_string(),
-                subject: "first".to_string(),
-                sanitized_subject_line: "sanitized-subject-line".to_string(),
-                author: user.to_owned(),
-                committer: user.to_owned(),
-            },
-        ];
+    #[test]
+    fn test_markdown_changelog() {
+        let change_gen = ChangelogGenerator::new();
 
-        let res1 = change_gen.markdown_changelog(&non_empty, Some(ver));
+        let res1 = change_gen.markdown_changelog(&entry(commits())).unwrap();
         assert!(res1.contains("## v1.2.3"));
         assert!(res1.contains("\n\n- second\n- first\n"));
 
-        let empty: Vec<Commit> = vec![];
-        let res2 = change_gen.markdown_changelog(&empty, None);
+        let res2 = change_gen.release_body(&entry(vec![])).unwrap();
         assert!(res2.ends_with("No commits since last version\n"));
     }
 
     #[test]
-    fn test_compact_changelog() {
-        let change_gen = ChangelogGenerator {};
+    fn test_markdown_changelog_tag() {
+        let change_gen = ChangelogGenerator::new();
 
-        let user = commit::User {
-            name: "name".to_string(),
-            email: "email".to_string(),
-            date: "date".to_string(),
-        };
+        let res = change_gen.markdown_changelog(&entry(commits())).unwrap();
+        assert_eq!(res, "## v1.2.3 (2020-10-04)\n\n- second\n- first\n");
+
+        let mut package_entry = entry(commits());
+        package_entry.release.tag = "service/1.2.3".to_string();
+        let res = change_gen.markdown_changelog(&package_entry).unwrap();
+        assert!(res.starts_with("## service/1.2.3 ("));
+
+        let res = change_gen.release_body(&entry(vec![])).unwrap();
+        assert_eq!(res, "## 2020-10-04\n\nNo commits since last version\n");
+    }
+
+    #[test]
+    fn test_markdown_changelog_updated_dependencies() {
+        let mut change_gen = ChangelogGenerator::for_package("packages/app");
+        change_gen.set_updated_dependencies(vec!["utils@0.4.0".to_string()]);
+
+        let res = change_gen.release_body(&entry(vec![])).unwrap();
+        assert!(res.ends_with("\n\n- Updated dependencies\n  - utils@0.4.0\n"));
+
+        assert_eq!(
+            change_gen.compact_changelog(&entry(vec![])).unwrap(),
+            " - Updated utils@0.4.0\n"
+        );
+    }
+
+    #[test]
+    fn test_compact_changelog() {
+        let change_gen = ChangelogGenerator::new();
 
-        let non_empty: Vec<Commit> = vec![
-            commit::Commit {
-                commit: "commit2".to_string(),
-                abbreviated_commit: "abb_commit2".to_string(),
-                refs: "refs2".to_string(),
-                commit_notes: "commit_notes".to_string(),
-                subject: "second".to_string(),
-                sanitized_subject_line: "sanitized-subject-line".to_string(),
-                author: user.to_owned(),
-                committer: user.to_owned(),
-            },
-            commit::Commit {
-                commit: "commit1".to_string(),
-                abbreviated_commit: "abb_commit1".to_string(),
-                refs: "refs1".to_string(),
-                commit_notes: "commit_notes".to_string(),
-                subject: "first".to_string(),
-                sanitized_subject_line: "sanitized-subject-line".to_string(),
-                author: user.to_owned(),
-                committer: user.to_owned(),
-            },
-        ];
         let non_empty_expected = " - second\n - first\n".to_string();
-        assert_eq!(change_gen.compact_changelog(&non_empty), non_empty_expected);
+        assert_eq!(
+            change_gen.compact_changelog(&entry(commits())).unwrap(),
+            non_empty_expected
+        );
 
-        let empty: Vec<Commit> = vec![];
         let empty_expected = "No commits since last version".to_string();
-        assert_eq!(change_gen.compact_changelog(&empty), empty_expected);
+        assert_eq!(
+            change_gen.compact_changelog(&entry(vec![])).unwrap(),
+            empty_expected
+        );
+    }
+
+    #[test]
+    fn test_promote_unreleased() {
+        let mut change_gen = ChangelogGenerator::new();
+        change_gen.set_format(ChangelogFormat::KeepAChangelog);
+        change_gen.set_links(RepositoryLinks::new("https://github.com/o/r"));
+
+        let contents = "# Changelog\n\n## [Unreleased]\n\n## [1.2.2] - 2020-09-01\n\n\
+                        [Unreleased]: https://github.com/o/r/compare/v1.2.2...HEAD\n";
+        let res = change_gen
+            .promote_unreleased(contents, &entry(vec![]), "## [1.2.3] - 2020-10-04\n")
+            .unwrap();
+
+        assert_eq!(
+            res,
+            "# Changelog\n\n## [Unreleased]\n\n## [1.2.3] - 2020-10-04\n\n## [1.2.2] - 2020-09-01\n\n\
+             [Unreleased]: https://github.com/o/r/compare/v1.2.3...HEAD\n\
+             [1.2.3]: https://github.com/o/r/compare/v1.2.2...v1.2.3\n"
+        );
+    }
+
+    #[test]
+    fn test_json_changelog() {
+        let change_gen = ChangelogGenerator::new();
+        let json = change_gen.json_changelog(&entry(commits())).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["version"], "1.2.3");
+        assert_eq!(value["previous_tag"], "v1.2.2");
+        assert_eq!(value["commits"][0]["subject"], "second");
+        assert_eq!(value["groups"][0]["title"], "Other Changes");
+    }
+
+    #[test]
+    fn test_compare_url() {
+        let mut change_gen = ChangelogGenerator::new();
+        let entry = entry(vec![]);
+        assert_eq!(change_gen.template_context(&entry).compare_url, None);
+
+        change_gen.set_links(RepositoryLinks::new("https://github.com/owner/repo"));
+        assert_eq!(
+            change_gen.template_context(&entry).compare_url.unwrap(),
+            "https://github.com/owner/repo/compare/v1.2.2...v1.2.3"
+        );
+    }
+
+    #[test]
+    fn test_changelog_entry_date() {
+        let dir = crate::git::enter_test_repo("changelog-date-test");
+        let old_commit = |message: &str| {
+            std::process::Command::new("git")
+                .args(["commit", "-q", "--allow-empty", "-m", message])
+                .env("GIT_COMMITTER_DATE", "2001-02-03T12:00:00Z")
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+        };
+        old_commit("feat: first");
+        std::process::Command::new("git")
+            .args(["tag", "v1.0.0"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        old_commit("fix: second");
+
+        let mut change_gen = ChangelogGenerator::new();
+        change_gen.set_dates(
+            ChangelogDates::from_config(&crate::config::ChangelogConfig {
+                timezone: crate::config::ChangelogTimezone::Utc,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let release = |version: &str, previous_tag: Option<&str>| ReleaseInfo {
+            version: version.to_string(),
+            tag: format!("v{}", version),
+            previous_version: None,
+            previous_tag: previous_tag.map(String::from),
+        };
+        let unreleased = change_gen
+            .changelog_entry("HEAD", release("1.1.0", Some("v1.0.0")))
+            .unwrap();
+        let released = change_gen
+            .changelog_entry("v1.0.0", release("1.0.0", None))
+            .unwrap();
+
+        change_gen.set_dates(
+            ChangelogDates::from_config(&crate::config::ChangelogConfig {
+                date: Some("2020-10-04".to_string()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let rebuilt = change_gen
+            .changelog_entry("v1.0.0", release("1.0.0", None))
+            .unwrap();
+        let overridden = change_gen
+            .changelog_entry("HEAD", release("1.1.0", Some("v1.0.0")))
+            .unwrap();
+        crate::git::leave_test_repo();
+
+        // HEAD is older than the release, which is dated today
+        assert_eq!(
+            unreleased.date,
+            chrono::Utc::now().format("%Y-%m-%d").to_string()
+        );
+        assert_eq!(unreleased.commits[0].subject, "fix: second");
+        assert_eq!(released.date, "2001-02-03");
+        assert_eq!(rebuilt.date, "2001-02-03");
+        assert_eq!(overridden.date, "2020-10-04");
     }
 }
@@ -0,0 +1,554 @@
+use crate::commit::Commit;
+use crate::config::{ChangelogConfig, ChangelogFormat};
+use crate::github::RepositoryLinks;
+use eyre::{Result, WrapErr};
+use minijinja::Environment;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+
+pub static ENTRY_TEMPLATE: &str = "entry";
+pub static RELEASE_TEMPLATE: &str = "release";
+pub static SUMMARY_TEMPLATE: &str = "summary";
+static CHANGES_TEMPLATE: &str = "changes";
+
+/// The list of changes, included by the entry and release templates.
+static DEFAULT_CHANGES: &str = r#"{% if commits or updated_dependencies %}
+{% for commit in commits %}
+- {{ commit.linked_subject }}{% if commit.url %} ([{{ commit.short_hash }}]({{ commit.url }})){% endif +%}
+{% endfor %}
+{% if updated_dependencies %}
+- Updated dependencies
+{% for dependency in updated_dependencies %}
+  - {{ dependency }}
+{% endfor %}
+{% endif %}
+{% else %}
+No commits since last version
+{% endif %}
+"#;
+
+/// The entry added to CHANGELOG.md.
+static DEFAULT_ENTRY: &str = r#"## {% if compare_url %}[{{ tag }}]({{ compare_url }}){% else %}{{ tag }}{% endif %} ({{ date }})
+
+{% include "changes" %}
+"#;
+
+/// The entry added to a CHANGELOG.md in the Keep a Changelog format.
+static KEEP_A_CHANGELOG_ENTRY: &str = r#"## [{{ version }}] - {{ date }}
+{% for section in sections %}
+
+### {{ section.title }}
+
+{% for commit in section.commits %}
+- {{ commit.linked_description }}
+{% endfor %}
+{% if section.title == "Changed" and updated_dependencies %}
+- Updated dependencies
+{% for dependency in updated_dependencies %}
+  - {{ dependency }}
+{% endfor %}
+{% endif %}
+{% else %}
+
+No commits since last version
+{% endfor %}
+"#;
+
+/// The body of the GitHub release.
+static DEFAULT_RELEASE: &str = r#"## {{ date }}
+
+{% include "changes" %}
+"#;
+
+/// The changes printed in the terminal after a release.
+static DEFAULT_SUMMARY: &str = r#"{% if commits or updated_dependencies %}
+{% for commit in commits %}
+ - {{ commit.subject }}
+{% endfor %}
+{% for dependency in updated_dependencies %}
+ - Updated {{ dependency }}
+{% endfor %}
+{% else %}
+No commits since last version{% endif %}
+"#;
+
+/// Titles of the commit groups by conventional commit type, in the order they are listed.
+static GROUP_TITLES: &[(&str, &str)] = &[
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance Improvements"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+];
+static OTHER_GROUP_TITLE: &str = "Other Changes";
+
+/// The sections of a Keep a Changelog entry, in the order they are listed.
+static SECTION_TITLES: &[&str] = &[
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+];
+static CHANGED_SECTION_TITLE: &str = "Changed";
+
+/// The values available in the changelog templates.
+#[derive(Debug, Serialize)]
+pub struct TemplateContext<'a> {
+    pub version: &'a str,
+    pub tag: &'a str,
+    pub previous_version: Option<&'a str>,
+    pub previous_tag: Option<&'a str>,
+    pub date: &'a str,
+    pub commits: Vec<CommitContext<'a>>,
+    /// Commits grouped by their conventional commit type
+    pub groups: Vec<CommitGroup<'a>>,
+    /// Commits in the Added, Changed, Deprecated, Removed, Fixed and Security sections
+    /// of Keep a Changelog
+    pub sections: Vec<CommitGroup<'a>>,
+    pub breaking_changes: Vec<CommitContext<'a>>,
+    /// Names of the commit authors, without duplicates
+    pub authors: Vec<&'a str>,
+    pub updated_dependencies: &'a [String],
+    pub repository_url: Option<&'a str>,
+    /// Link to the changes between the previous and this release
+    pub compare_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitContext<'a> {
+    pub hash: &'a str,
+    pub short_hash: &'a str,
+    /// Link to the commit, `None` without a repository
+    pub url: Option<String>,
+    pub subject: &'a str,
+    /// The subject with `#123` references linked to their issue or pull request
+    pub linked_subject: String,
+    /// Conventional commit type, e.g. `feat` or `fix`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    /// The subject without the conventional commit prefix
+    pub description: String,
+    pub linked_description: String,
+    pub breaking: bool,
+    pub author: &'a str,
+    pub email: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitGroup<'a> {
+    pub title: &'static str,
+    pub commits: Vec<CommitContext<'a>>,
+}
+
+/// Matches `#123` references, but not anchors in URLs or references that are already links.
+static REFERENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|[^\w\[/&])#(\d+)\b").unwrap());
+
+/// Links `#123` references to their issue or pull request.
+fn link_references(text: &str, links: Option<&RepositoryLinks>) -> String {
+    let links = match links {
+        Some(links) => links,
+        None => return text.to_owned(),
+    };
+    REFERENCE
+        .replace_all(text, |caps: &regex::Captures| {
+            format!("{}[#{}]({})", &caps[1], &caps[2], links.issue(&caps[2]))
+        })
+        .to_string()
+}
+
+impl<'a> CommitContext<'a> {
+    pub fn new(commit: &'a Commit, links: Option<&RepositoryLinks>) -> Self {
+        let (kind, scope, description, breaking) = match commit.conventional() {
+            Some(conventional) => (
+                Some(conventional.kind),
+                conventional.scope,
+                conventional.description,
+                conventional.breaking,
+            ),
+            None => (None, None, commit.subject.to_string(), false),
+        };
+
+        CommitContext {
+            hash: &commit.commit,
+            short_hash: &commit.abbreviated_commit,
+            url: links.map(|links| links.commit(&commit.commit)),
+            subject: &commit.subject,
+            linked_subject: link_references(&commit.subject, links),
+            kind,
+            scope,
+            breaking: breaking || commit.has_breaking_change_note(),
+            linked_description: link_references(&description, links),
+            description,
+            author: &commit.author.name,
+            email: &commit.author.email,
+        }
+    }
+}
+
+/// The group of a commit, by its type.
+fn group_title(commit: &CommitContext) -> &'static str {
+    GROUP_TITLES
+        .iter()
+        .find(|(kind, _)| commit.kind.as_deref() == Some(*kind))
+        .map(|(_, title)| *title)
+        .unwrap_or(OTHER_GROUP_TITLE)
+}
+
+/// The Keep a Changelog section of a commit, by its type and scope.
+fn section_title(commit: &CommitContext) -> &'static str {
+    if commit.kind.as_deref() == Some("security") || commit.scope.as_deref() == Some("security") {
+        return "Security";
+    }
+    match commit.kind.as_deref() {
+        Some("feat") => "Added",
+        Some("fix") => "Fixed",
+        Some("deprecate") => "Deprecated",
+        Some("remove") | Some("revert") => "Removed",
+        _ => CHANGED_SECTION_TITLE,
+    }
+}
+
+/// Groups commits under the given titles, in their order.
+fn group_commits<'a>(
+    commits: &[CommitContext<'a>],
+    titles: impl Iterator<Item = &'static str>,
+    title_of: fn(&CommitContext) -> &'static str,
+) -> Vec<CommitGroup<'a>> {
+    titles
+        .map(|title| CommitGroup {
+            title,
+            commits: commits
+                .iter()
+                .filter(|commit| title_of(commit) == title)
+                .cloned()
+                .collect(),
+        })
+        .collect()
+}
+
+impl<'a> TemplateContext<'a> {
+    pub fn commits(mut self, commits: &'a [Commit], links: Option<&RepositoryLinks>) -> Self {
+        self.commits = commits
+            .iter()
+            .map(|commit| CommitContext::new(commit, links))
+            .collect();
+        let group_titles = GROUP_TITLES
+            .iter()
+            .map(|(_, title)| *title)
+            .chain([OTHER_GROUP_TITLE]);
+        self.groups = group_commits(&self.commits, group_titles, group_title)
+            .into_iter()
+            .filter(|group| !group.commits.is_empty())
+            .collect();
+
+        // Updated dependencies are listed under Changed
+        let has_dependencies = !self.updated_dependencies.is_empty();
+        self.sections = group_commits(&self.commits, SECTION_TITLES.iter().copied(), section_title)
+            .into_iter()
+            .filter(|section| {
+                !section.commits.is_empty()
+                    || (has_dependencies && section.title == CHANGED_SECTION_TITLE)
+            })
+            .collect();
+        self.breaking_changes = self
+            .commits
+            .iter()
+            .filter(|commit| commit.breaking)
+            .cloned()
+            .collect();
+
+        self.authors = vec![];
+        for commit in &self.commits {
+            if !self.authors.contains(&commit.author) {
+                self.authors.push(commit.author);
+            }
+        }
+        self
+    }
+}
+
+/// The templates changelogs are rendered with, the built-in defaults
+/// unless the config points to other template files.
+pub struct ChangelogTemplates {
+    env: Environment<'static>,
+}
+
+impl ChangelogTemplates {
+    pub fn new() -> Self {
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        env.set_keep_trailing_newline(true);
+
+        for (name, source) in [
+            (CHANGES_TEMPLATE, DEFAULT_CHANGES),
+            (ENTRY_TEMPLATE, DEFAULT_ENTRY),
+            (RELEASE_TEMPLATE, DEFAULT_RELEASE),
+            (SUMMARY_TEMPLATE, DEFAULT_SUMMARY),
+        ] {
+            env.add_template(name, source)
+                .expect("the default templates are valid");
+        }
+
+        ChangelogTemplates { env }
+    }
+
+    /// Replaces the default templates with the ones of the changelog format
+    /// and the template files from the config.
+    pub fn load(config: &ChangelogConfig) -> Result<Self> {
+        let mut templates = ChangelogTemplates::new();
+        if config.format == ChangelogFormat::KeepAChangelog {
+            templates.add(ENTRY_TEMPLATE, KEEP_A_CHANGELOG_ENTRY.to_string())?;
+        }
+
+        for (name, path) in [
+            (ENTRY_TEMPLATE, &config.entry_template),
+            (RELEASE_TEMPLATE, &config.release_template),
+            (SUMMARY_TEMPLATE, &config.summary_template),
+        ] {
+            if let Some(path) = path {
+                let source = fs::read_to_string(path)
+                    .wrap_err_with(|| format!("Could not read the {} template {}", name, path))?;
+                templates.add(name, source)?;
+            }
+        }
+
+        Ok(templates)
+    }
+
+    fn add(&mut self, name: &'static str, source: String) -> Result<()> {
+        self.env
+            .add_template_owned(name, source)
+            .wrap_err_with(|| format!("Invalid {} template", name))
+    }
+
+    pub fn render(&self, name: &str, context: &TemplateContext) -> Result<String> {
+        let template = self.env.get_template(name)?;
+        template
+            .render(context)
+            .wrap_err_with(|| format!("Could not render the {} template", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TemplateContext<'static> {
+        TemplateContext {
+            version: "1.3.0",
+            tag: "v1.3.0",
+            previous_version: Some("1.2.0"),
+            previous_tag: Some("v1.2.0"),
+            date: "2022-10-30",
+            commits: vec![],
+            groups: vec![],
+            sections: vec![],
+            breaking_changes: vec![],
+            authors: vec![],
+            updated_dependencies: &[],
+            repository_url: None,
+            compare_url: None,
+        }
+    }
+
+    #[test]
+    fn test_commit_context() {
+        let commit = Commit::for_test("abcdef0123", "feat(cli)!: drop the -x flag", "Jane");
+        let context = CommitContext::new(&commit, None);
+
+        assert_eq!(context.kind.as_deref(), Some("feat"));
+        assert_eq!(context.scope.as_deref(), Some("cli"));
+        assert_eq!(context.description, "drop the -x flag");
+        assert!(context.breaking);
+
+        let commit = Commit::for_test("abcdef0123", "Update README", "Jane");
+        let context = CommitContext::new(&commit, None);
+        assert_eq!(context.kind, None);
+        assert_eq!(context.description, "Update README");
+        assert!(!context.breaking);
+    }
+
+    #[test]
+    fn test_breaking_change_note() {
+        let dir = crate::git::enter_test_repo("breaking-change-test");
+        let commit = |message: &str| {
+            std::process::Command::new("git")
+                .args(["commit", "-q", "--allow-empty", "-m", message])
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+        };
+        commit("feat: new config file\n\nBREAKING CHANGE: the old one is no longer read");
+        commit("fix: typo\n\nMentions a BREAKING CHANGE in passing");
+        let commits = crate::git::commits_in_log(&["HEAD~2..HEAD".to_string()]);
+        crate::git::leave_test_repo();
+
+        let commits = commits.unwrap();
+        assert_eq!(commits[1].subject, "feat: new config file");
+        assert!(CommitContext::new(&commits[1], None).breaking);
+        assert_eq!(commits[0].subject, "fix: typo");
+        assert!(!CommitContext::new(&commits[0], None).breaking);
+
+        let context = context().commits(&commits, None);
+        assert_eq!(context.breaking_changes.len(), 1);
+        assert_eq!(context.breaking_changes[0].description, "new config file");
+    }
+
+    #[test]
+    fn test_groups_and_authors() {
+        let commits = vec![
+            Commit::for_test("1111111111", "fix: crash on empty file", "Jane"),
+            Commit::for_test("2222222222", "chore: bump deps", "John"),
+            Commit::for_test("3333333333", "feat: add --dry-run", "Jane"),
+        ];
+        let context = context().commits(&commits, None);
+
+        let titles = context.groups.iter().map(|g| g.title).collect::<Vec<_>>();
+        assert_eq!(titles, vec!["Features", "Bug Fixes", "Other Changes"]);
+        assert_eq!(context.authors, vec!["Jane", "John"]);
+        assert!(context.breaking_changes.is_empty());
+    }
+
+    #[test]
+    fn test_render_defaults() {
+        let templates = ChangelogTemplates::new();
+        let commits = vec![
+            Commit::for_test("1111111111", "second", "Jane"),
+            Commit::for_test("2222222222", "first", "Jane"),
+        ];
+
+        let non_empty = context().commits(&commits, None);
+        assert_eq!(
+            templates.render(ENTRY_TEMPLATE, &non_empty).unwrap(),
+            "## v1.3.0 (2022-10-30)\n\n- second\n- first\n"
+        );
+        assert_eq!(
+            templates.render(RELEASE_TEMPLATE, &non_empty).unwrap(),
+            "## 2022-10-30\n\n- second\n- first\n"
+        );
+        assert_eq!(
+            templates.render(SUMMARY_TEMPLATE, &non_empty).unwrap(),
+            " - second\n - first\n"
+        );
+
+        let empty = context();
+        assert_eq!(
+            templates.render(ENTRY_TEMPLATE, &empty).unwrap(),
+            "## v1.3.0 (2022-10-30)\n\nNo commits since last version\n"
+        );
+        assert_eq!(
+            templates.render(SUMMARY_TEMPLATE, &empty).unwrap(),
+            "No commits since last version"
+        );
+    }
+
+    #[test]
+    fn test_render_links() {
+        let links = RepositoryLinks::new("https://github.com/owner/repo");
+        let templates = ChangelogTemplates::new();
+        let commits = vec![Commit::for_test(
+            "1111111111",
+            "fix: crash on empty file (#12)",
+            "Jane",
+        )];
+
+        let mut linked = context().commits(&commits, Some(&links));
+        linked.compare_url = Some(links.compare("v1.2.0", "v1.3.0"));
+        assert_eq!(
+            templates.render(ENTRY_TEMPLATE, &linked).unwrap(),
+            "## [v1.3.0](https://github.com/owner/repo/compare/v1.2.0...v1.3.0) (2022-10-30)\n\n\
+             - fix: crash on empty file ([#12](https://github.com/owner/repo/issues/12)) \
+             ([1111111](https://github.com/owner/repo/commit/1111111111))\n"
+        );
+    }
+
+    #[test]
+    fn test_render_keep_a_changelog() {
+        let config = ChangelogConfig {
+            format: ChangelogFormat::KeepAChangelog,
+            ..ChangelogConfig::default()
+        };
+        let templates = ChangelogTemplates::load(&config).unwrap();
+        let commits = vec![
+            Commit::for_test("1111111111", "fix(security): escape user input", "Jane"),
+            Commit::for_test("2222222222", "fix: crash on empty file", "Jane"),
+            Commit::for_test("3333333333", "feat: add --dry-run", "John"),
+            Commit::for_test("4444444444", "Tidy up the README", "John"),
+        ];
+
+        assert_eq!(
+            templates
+                .render(ENTRY_TEMPLATE, &context().commits(&commits, None))
+                .unwrap(),
+            "## [1.3.0] - 2022-10-30\n\n### Added\n\n- add --dry-run\n\n\
+             ### Changed\n\n- Tidy up the README\n\n### Fixed\n\n- crash on empty file\n\n\
+             ### Security\n\n- escape user input\n"
+        );
+
+        let dependencies = vec!["utils@0.4.0".to_string()];
+        let mut empty = context();
+        empty.updated_dependencies = &dependencies;
+        assert_eq!(
+            templates
+                .render(ENTRY_TEMPLATE, &empty.commits(&[], None))
+                .unwrap(),
+            "## [1.3.0] - 2022-10-30\n\n### Changed\n\n- Updated dependencies\n  - utils@0.4.0\n"
+        );
+        assert_eq!(
+            templates.render(ENTRY_TEMPLATE, &context()).unwrap(),
+            "## [1.3.0] - 2022-10-30\n\nNo commits since last version\n"
+        );
+    }
+
+    #[test]
+    fn test_link_references() {
+        let links = RepositoryLinks::new("https://github.com/owner/repo/");
+
+        assert_eq!(
+            link_references("#3 and #45, not a#1, &#39; or org/repo#2", Some(&links)),
+            "[#3](https://github.com/owner/repo/issues/3) and \
+             [#45](https://github.com/owner/repo/issues/45), not a#1, &#39; or org/repo#2"
+        );
+        assert_eq!(link_references("fix (#3)", None), "fix (#3)");
+    }
+
+    #[test]
+    fn test_load_template() {
+        let path = "test_entry_template.md.j2";
+        fs::write(
+            path,
+            "## {{ version }}\n{% for group in groups %}\n### {{ group.title }}\n{% for commit in group.commits %}\n- {{ commit.description }} ({{ commit.author }})\n{% endfor %}\n{% endfor %}\n",
+        )
+        .unwrap();
+
+        let config = ChangelogConfig {
+            entry_template: Some(path.to_string()),
+            ..ChangelogConfig::default()
+        };
+        let templates = ChangelogTemplates::load(&config).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let commits = vec![
+            Commit::for_test("1111111111", "fix: crash on empty file", "Jane"),
+            Commit::for_test("3333333333", "feat: add --dry-run", "John"),
+        ];
+        assert_eq!(
+            templates
+                .render(ENTRY_TEMPLATE, &context().commits(&commits, None))
+                .unwrap(),
+            "## 1.3.0\n### Features\n- add --dry-run (John)\n### Bug Fixes\n- crash on empty file (Jane)\n"
+        );
+
+        let missing = ChangelogConfig {
+            summary_template: Some("no-such-template.j2".to_string()),
+            ..ChangelogConfig::default()
+        };
+        assert!(ChangelogTemplates::load(&missing).is_err());
+    }
+}
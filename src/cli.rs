@@ -1,4 +1,6 @@
-use clap::Parser;
+use crate::config::{VersionFileConfig, CONFIG_FILE_PATH};
+use crate::git::{CommitOptions, GitConfig, PushOptions, TagOptions};
+use clap::{Parser, Subcommand};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -6,24 +8,202 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 #[derive(Parser, Debug)]
 #[command(version = VERSION)]
 pub struct CliArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     /// The Github project to create a release of
-    #[arg(short, long, value_name = "REPO", required = true)]
-    pub repo: String,
+    #[arg(short, long, value_name = "REPO", global = true)]
+    pub repo: Option<String>,
     /// Which type of release to create
-    #[arg(short, long = "type", value_name = "TYPE", default_value = "minor")]
+    #[arg(
+        short,
+        long = "type",
+        value_name = "TYPE",
+        default_value = "minor",
+        global = true
+    )]
     pub version_type: String,
-    /// Which branch of the repository to use
-    #[arg(short = 'f', long = "file", value_name = "FILE", required = true)]
-    pub version_file: String,
+    /// The file containing the version of the project
+    #[arg(short = 'f', long = "file", value_name = "FILE")]
+    pub version_file: Option<String>,
+    /// Regex locating the version in the version file, the first capture group is the version
+    #[arg(short = 'p', long = "pattern", value_name = "REGEX")]
+    pub pattern: Option<String>,
+    /// Key path of the version in TOML and JSON files, e.g. `expo.version` or `/expo/version`
+    #[arg(
+        short = 'k',
+        long = "key",
+        value_name = "PATH",
+        conflicts_with = "pattern"
+    )]
+    pub key: Option<String>,
+    /// Increment the Android versionCode whenever the version is updated (Gradle and properties files)
+    #[arg(long = "version-code")]
+    pub version_code: bool,
+    /// Template for the tag names, e.g. `release-{version}`, `{version}` or `service/{version}`
+    #[arg(long = "tag-template", value_name = "TEMPLATE", global = true)]
+    pub tag_template: Option<String>,
     /// The Github personal access token
-    #[arg(short = 't', long = "token", value_name = "TOKEN")]
-    pub personal_token: String,
+    #[arg(short = 't', long = "token", value_name = "TOKEN", global = true)]
+    pub personal_token: Option<String>,
     /// Specifies the main branch the project uses
     #[arg(
         short = 'b',
         long = "branch",
         value_name = "BRANCH",
-        default_value = "main"
+        default_value = "main",
+        global = true
     )]
     pub main_branch: String,
+    /// Open a release pull request instead of pushing to the main branch,
+    /// run again after merging it to tag and publish the release
+    #[arg(long = "pull-request", global = true, conflicts_with = "no_push")]
+    pub pull_request: bool,
+    /// The remote to push to, can be given several times
+    #[arg(
+        long = "remote",
+        value_name = "REMOTE",
+        default_value = "origin",
+        global = true
+    )]
+    pub remotes: Vec<String>,
+    /// Push the branch and the tag together, so neither is pushed if the other is rejected
+    #[arg(long, global = true)]
+    pub atomic: bool,
+    /// Commit and tag the release locally without pushing it or creating a GitHub release
+    #[arg(long = "no-push", global = true)]
+    pub no_push: bool,
+    /// Release from a maintenance branch like `release/1.x`, see --branch
+    #[arg(long, global = true)]
+    pub maintenance: bool,
+    /// Create annotated tags with the changelog entry as the tag message
+    #[arg(long = "annotate-tags", global = true)]
+    pub annotate_tags: bool,
+    /// Sign the release tags, the signature is verified before pushing
+    #[arg(long = "sign-tags", global = true)]
+    pub sign_tags: bool,
+    /// Sign the release commits
+    #[arg(long = "sign-commits", global = true)]
+    pub sign_commits: bool,
+    /// Format of the signatures
+    #[arg(
+        long = "signing-format",
+        value_name = "FORMAT",
+        value_parser = ["gpg", "ssh", "x509"],
+        global = true
+    )]
+    pub signing_format: Option<String>,
+    /// The key to sign with, the default key of the committer when not set
+    #[arg(long = "signing-key", value_name = "KEY", global = true)]
+    pub signing_key: Option<String>,
+    /// Name of the author and committer of the release commits and tags
+    #[arg(long = "git-user-name", value_name = "NAME", global = true)]
+    pub git_user_name: Option<String>,
+    /// Email of the author and committer of the release commits and tags
+    #[arg(long = "git-user-email", value_name = "EMAIL", global = true)]
+    pub git_user_email: Option<String>,
+    /// Trailer added to the release commits, e.g. `Signed-off-by: Bot <bot@example.com>`
+    #[arg(long = "trailer", value_name = "TRAILER", global = true)]
+    pub trailers: Vec<String>,
+    /// Date of the changelog entries, e.g. `2020-10-04` or an RFC 3339 timestamp,
+    /// the time of the released commit by default
+    #[arg(long, value_name = "DATE", global = true)]
+    pub date: Option<String>,
+    /// The git-releaser config file
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "FILE",
+        default_value = CONFIG_FILE_PATH,
+        global = true
+    )]
+    pub config: String,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Release a single package of a monorepo
+    Release {
+        /// Name of the package in the config file
+        package: String,
+    },
+    /// List the monorepo packages with commits since their last release
+    Changed {
+        /// Release every changed package instead of listing them
+        #[arg(long)]
+        release: bool,
+    },
+    /// Work on the changelog of the repository or of a monorepo package
+    Changelog {
+        /// Name of the package in the config file, the whole repository when unset
+        package: Option<String>,
+        /// Regenerate the whole changelog from the release tags
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        rebuild: bool,
+        /// Preview the changes since this tag, the previous release by default
+        #[arg(long, value_name = "TAG")]
+        from: Option<String>,
+        /// Preview the changes up to this ref, a release tag previews that release
+        #[arg(long, value_name = "REF")]
+        to: Option<String>,
+        /// How to print the preview
+        #[arg(
+            long,
+            value_name = "FORMAT",
+            value_parser = ["markdown", "plain", "json"],
+            default_value = "markdown"
+        )]
+        output: String,
+        /// Also list the commits left out of the preview and why
+        #[arg(long, conflicts_with = "rebuild")]
+        explain: bool,
+    },
+}
+
+impl CliArgs {
+    /// The version file given on the command line, if any.
+    pub fn version_file_config(&self) -> Option<VersionFileConfig> {
+        self.version_file.as_ref().map(|file| VersionFileConfig {
+            file: file.to_owned(),
+            pattern: self.pattern.to_owned(),
+            key: self.key.to_owned(),
+            version_code: self.version_code,
+        })
+    }
+
+    fn git_config(&self) -> GitConfig {
+        GitConfig {
+            signing_format: self.signing_format.to_owned(),
+            signing_key: self.signing_key.to_owned(),
+            user_name: self.git_user_name.to_owned(),
+            user_email: self.git_user_email.to_owned(),
+        }
+    }
+
+    pub fn tag_options(&self) -> TagOptions {
+        TagOptions {
+            annotate: self.annotate_tags,
+            sign: self.sign_tags,
+            config: self.git_config(),
+        }
+    }
+
+    /// How to push the release, `None` with --no-push.
+    pub fn push_options(&self) -> Option<PushOptions> {
+        if self.no_push {
+            return None;
+        }
+
+        Some(PushOptions {
+            remotes: self.remotes.to_owned(),
+            atomic: self.atomic,
+        })
+    }
+
+    pub fn commit_options(&self) -> CommitOptions {
+        CommitOptions {
+            sign: self.sign_commits,
+            trailers: self.trailers.to_owned(),
+            config: self.git_config(),
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -17,11 +19,66 @@ pub struct Commit {
     pub commit_notes: String,
     pub author: User,
     pub committer: User,
+    /// Hashes of the parent commits, separated by spaces
+    #[serde(default)]
+    pub parents: String,
 }
 
+/// The parts of a conventional commit subject, e.g. `feat(cli)!: add --dry-run`.
+#[derive(Debug, PartialEq)]
+pub struct Conventional {
+    /// The type, lowercased
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+static CONVENTIONAL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<type>[a-zA-Z]+)(\((?P<scope>[^)]+)\))?(?P<breaking>!)?: (?P<description>.+)$")
+        .unwrap()
+});
+
 impl Commit {
-    pub fn compact(&self) -> String {
-        self.subject.to_string()
+    /// Parses the subject as a conventional commit, `None` for other subjects.
+    pub fn conventional(&self) -> Option<Conventional> {
+        CONVENTIONAL
+            .captures(&self.subject)
+            .map(|caps| Conventional {
+                kind: caps["type"].to_lowercase(),
+                scope: caps.name("scope").map(|m| m.as_str().to_string()),
+                breaking: caps.name("breaking").is_some(),
+                description: caps["description"].to_string(),
+            })
+    }
+
+    /// Whether the body has a `BREAKING CHANGE:` footer.
+    pub fn has_breaking_change_note(&self) -> bool {
+        self.commit_notes.lines().any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        })
+    }
+
+    pub fn is_merge(&self) -> bool {
+        self.parents.split_whitespace().count() > 1
+    }
+}
+
+#[cfg(test)]
+impl Commit {
+    /// A commit with a single parent, for tests.
+    pub fn for_test(hash: &str, subject: &str, author: &str) -> Self {
+        Commit {
+            commit: hash.to_string(),
+            abbreviated_commit: hash[..7].to_string(),
+            subject: subject.to_string(),
+            author: User {
+                name: author.to_string(),
+                ..User::default()
+            },
+            parents: "parent".to_string(),
+            ..Commit::default()
+        }
     }
 }
 
@@ -94,8 +151,46 @@ mod tests {
         let commit = Commit::from(data);
 
         assert_eq!(
-            commit.compact(),
+            commit.subject,
             "ci: add unit test, format, clippy, audit actions (#2)"
         );
     }
+
+    #[test]
+    fn test_conventional() {
+        let commit = Commit {
+            subject: "Feat(cli)!: add --dry-run".to_string(),
+            ..Commit::default()
+        };
+
+        assert_eq!(
+            commit.conventional(),
+            Some(Conventional {
+                kind: "feat".to_string(),
+                scope: Some("cli".to_string()),
+                breaking: true,
+                description: "add --dry-run".to_string(),
+            })
+        );
+
+        let commit = Commit {
+            subject: "Merge branch 'main'".to_string(),
+            ..Commit::default()
+        };
+        assert_eq!(commit.conventional(), None);
+    }
+
+    #[test]
+    fn test_is_merge() {
+        let commit = Commit::for_test("d41902f4ac", "Merge branch 'main'", "Jane");
+        assert!(!commit.is_merge());
+
+        let commit = Commit {
+            parents: "d41902f aa58f3d".to_string(),
+            ..commit
+        };
+        assert!(commit.is_merge());
+
+        assert!(!Commit::default().is_merge());
+    }
 }
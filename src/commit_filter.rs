@@ -0,0 +1,304 @@
+use crate::commit::Commit;
+use crate::config::{ChangelogConfig, CommitRuleConfig};
+use crate::git::{changed_files, commit_trailers};
+use eyre::{Result, WrapErr};
+use regex::Regex;
+use std::path::Path;
+
+/// A compiled commit rule, see `CommitRuleConfig`.
+#[derive(Debug, Clone)]
+pub struct CommitRule {
+    subject: Option<Regex>,
+    author: Option<Regex>,
+    kind: Option<String>,
+    trailer: Option<Regex>,
+    paths: Vec<String>,
+    merge: Option<bool>,
+}
+
+fn regex(field: &str, pattern: &Option<String>) -> Result<Option<Regex>> {
+    pattern
+        .as_deref()
+        .map(|pattern| {
+            Regex::new(pattern).wrap_err_with(|| format!("Invalid {} regex '{}'", field, pattern))
+        })
+        .transpose()
+}
+
+impl CommitRule {
+    pub fn from_config(config: &CommitRuleConfig) -> Result<Self> {
+        let rule = CommitRule {
+            subject: regex("subject", &config.subject)?,
+            author: regex("author", &config.author)?,
+            kind: config.kind.as_ref().map(|kind| kind.to_lowercase()),
+            trailer: regex("trailer", &config.trailer)?,
+            paths: config.paths.to_owned(),
+            merge: config.merge,
+        };
+        if rule.describe().is_empty() {
+            return Err(eyre!("A changelog rule needs at least one field"));
+        }
+
+        Ok(rule)
+    }
+
+    /// Checks the fields read from the log first, then the ones that need a git call.
+    pub fn matches(&self, commit: &Commit) -> Result<bool> {
+        if let Some(merge) = self.merge {
+            if commit.is_merge() != merge {
+                return Ok(false);
+            }
+        }
+        if let Some(subject) = &self.subject {
+            if !subject.is_match(&commit.subject) {
+                return Ok(false);
+            }
+        }
+        if let Some(author) = &self.author {
+            if !author.is_match(&commit.author.name) && !author.is_match(&commit.author.email) {
+                return Ok(false);
+            }
+        }
+        if let Some(kind) = &self.kind {
+            if commit.conventional().map(|conventional| conventional.kind) != Some(kind.to_owned())
+            {
+                return Ok(false);
+            }
+        }
+        if let Some(trailer) = &self.trailer {
+            let trailers = commit_trailers(&commit.commit)?;
+            if !trailers.iter().any(|line| trailer.is_match(line)) {
+                return Ok(false);
+            }
+        }
+        if !self.paths.is_empty() {
+            let files = changed_files(&commit.commit)?;
+            let in_paths = |file: &String| {
+                self.paths
+                    .iter()
+                    .any(|path| Path::new(file).starts_with(path))
+            };
+            if files.is_empty() || !files.iter().all(in_paths) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The fields of the rule, e.g. `author =~ dependabot, type = chore`.
+    pub fn describe(&self) -> String {
+        let mut fields = vec![];
+        if let Some(merge) = self.merge {
+            fields.push(format!("merge = {}", merge));
+        }
+        if let Some(subject) = &self.subject {
+            fields.push(format!("subject =~ {}", subject));
+        }
+        if let Some(author) = &self.author {
+            fields.push(format!("author =~ {}", author));
+        }
+        if let Some(kind) = &self.kind {
+            fields.push(format!("type = {}", kind));
+        }
+        if let Some(trailer) = &self.trailer {
+            fields.push(format!("trailer =~ {}", trailer));
+        }
+        if !self.paths.is_empty() {
+            fields.push(format!("paths = {}", self.paths.join(", ")));
+        }
+        fields.join(", ")
+    }
+}
+
+/// A commit left out of the changelog and why.
+#[derive(Debug)]
+pub struct DroppedCommit {
+    pub commit: Commit,
+    pub reason: String,
+}
+
+/// Picks the commits listed in the changelog.
+#[derive(Debug, Clone, Default)]
+pub struct CommitFilter {
+    /// Subjects of the commits made by releases
+    release_commits: Vec<Regex>,
+    exclude: Vec<CommitRule>,
+    include: Vec<CommitRule>,
+}
+
+impl CommitFilter {
+    pub fn new(release_commits: Vec<Regex>, config: &ChangelogConfig) -> Result<Self> {
+        let rules = |rules: &[CommitRuleConfig]| {
+            rules
+                .iter()
+                .map(CommitRule::from_config)
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(CommitFilter {
+            release_commits,
+            exclude: rules(&config.exclude)?,
+            include: rules(&config.include)?,
+        })
+    }
+
+    /// Splits the commits into the ones to list and the ones left out.
+    pub fn apply(&self, commits: Vec<Commit>) -> Result<(Vec<Commit>, Vec<DroppedCommit>)> {
+        let mut kept = vec![];
+        let mut dropped = vec![];
+        for commit in commits {
+            match self.reason_to_drop(&commit)? {
+                Some(reason) => {
+                    debug!(
+                        "🚮 Leaving out {} {}: {}",
+                        commit.abbreviated_commit, commit.subject, reason
+                    );
+                    dropped.push(DroppedCommit { commit, reason });
+                }
+                None => kept.push(commit),
+            }
+        }
+
+        Ok((kept, dropped))
+    }
+
+    fn reason_to_drop(&self, commit: &Commit) -> Result<Option<String>> {
+        if self
+            .release_commits
+            .iter()
+            .any(|pattern| pattern.is_match(&commit.subject))
+        {
+            return Ok(Some("release commit".to_string()));
+        }
+        for rule in &self.exclude {
+            if rule.matches(commit)? {
+                return Ok(Some(format!("excluded by {}", rule.describe())));
+            }
+        }
+        if self.include.is_empty() {
+            return Ok(None);
+        }
+        for rule in &self.include {
+            if rule.matches(commit)? {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some("not matched by an include rule".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(toml: &str) -> CommitRuleConfig {
+        toml_edit::easy::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let dependabot =
+            CommitRule::from_config(&rule("author = 'dependabot\\[bot\\]'\ntype = 'chore'"))
+                .unwrap();
+        assert!(dependabot
+            .matches(&Commit::for_test(
+                "1111111111",
+                "chore(deps): bump regex",
+                "dependabot[bot]"
+            ))
+            .unwrap());
+        assert!(!dependabot
+            .matches(&Commit::for_test(
+                "1111111111",
+                "chore(deps): bump regex",
+                "Jane"
+            ))
+            .unwrap());
+        assert!(!dependabot
+            .matches(&Commit::for_test(
+                "1111111111",
+                "fix: pin regex",
+                "dependabot[bot]"
+            ))
+            .unwrap());
+        assert_eq!(
+            dependabot.describe(),
+            "author =~ dependabot\\[bot\\], type = chore"
+        );
+
+        let merges = CommitRule::from_config(&rule("merge = true")).unwrap();
+        assert!(merges
+            .matches(&Commit {
+                parents: "a b".to_string(),
+                ..Commit::for_test("1111111111", "Merge pull request #3", "Jane")
+            })
+            .unwrap());
+        assert!(!merges
+            .matches(&Commit::for_test("1111111111", "fix: crash", "Jane"))
+            .unwrap());
+
+        assert!(CommitRule::from_config(&rule("")).is_err());
+        assert!(CommitRule::from_config(&rule("subject = '['")).is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let config = ChangelogConfig {
+            exclude: vec![
+                rule("subject = '\\[skip changelog\\]'"),
+                rule("merge = true"),
+            ],
+            ..ChangelogConfig::default()
+        };
+        let filter =
+            CommitFilter::new(vec![Regex::new("^chore: releasing .+$").unwrap()], &config).unwrap();
+
+        let commits = vec![
+            Commit::for_test("1111111111", "chore: releasing 1.2.3", "Jane"),
+            Commit::for_test("1111111111", "feat: release notes", "Jane"),
+            Commit::for_test("1111111111", "ci: cache builds [skip changelog]", "Jane"),
+            Commit {
+                parents: "a b".to_string(),
+                ..Commit::for_test("1111111111", "Merge branch 'main'", "Jane")
+            },
+        ];
+        let (kept, dropped) = filter.apply(commits).unwrap();
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].subject, "feat: release notes");
+        let reasons = dropped
+            .iter()
+            .map(|d| d.reason.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                "release commit",
+                "excluded by subject =~ \\[skip changelog\\]",
+                "excluded by merge = true"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_include() {
+        let config = ChangelogConfig {
+            include: vec![rule("type = 'feat'"), rule("type = 'fix'")],
+            ..ChangelogConfig::default()
+        };
+        let filter = CommitFilter::new(vec![], &config).unwrap();
+
+        let commits = vec![
+            Commit::for_test("1111111111", "feat: dark mode", "Jane"),
+            Commit::for_test("1111111111", "docs: typo", "Jane"),
+            Commit::for_test("1111111111", "Fix: crash", "Jane"),
+        ];
+        let (kept, dropped) = filter.apply(commits).unwrap();
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(dropped[0].commit.subject, "docs: typo");
+        assert_eq!(dropped[0].reason, "not matched by an include rule");
+    }
+}
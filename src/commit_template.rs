@@ -0,0 +1,143 @@
+use crate::config::CommitsConfig;
+use eyre::Result;
+use regex::Regex;
+use semver::Version;
+
+static NAME_PLACEHOLDER: &str = "{name}";
+static VERSION_PLACEHOLDER: &str = "{version}";
+static TAG_PLACEHOLDER: &str = "{tag}";
+
+/// Template for a release commit message, e.g. `chore: releasing {version}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitTemplate {
+    template: String,
+}
+
+impl CommitTemplate {
+    pub fn new(template: &str) -> Self {
+        CommitTemplate {
+            template: template.to_owned(),
+        }
+    }
+
+    /// Creates the commit message for a version of the named package.
+    pub fn format(&self, name: &str, version: &Version, tag: &str) -> String {
+        self.template
+            .replace(NAME_PLACEHOLDER, name)
+            .replace(VERSION_PLACEHOLDER, &version.to_string())
+            .replace(TAG_PLACEHOLDER, tag)
+    }
+
+    /// Matches the subject line of the commits created by this template.
+    pub fn subject_pattern(&self) -> Result<Regex> {
+        let subject = self.template.lines().next().unwrap_or_default();
+        let pattern = [NAME_PLACEHOLDER, VERSION_PLACEHOLDER, TAG_PLACEHOLDER]
+            .iter()
+            .fold(regex::escape(subject), |pattern, placeholder| {
+                pattern.replace(&regex::escape(placeholder), ".+")
+            });
+
+        Ok(Regex::new(&format!("^{}$", pattern))?)
+    }
+}
+
+/// The commits made by a release.
+#[derive(Debug, Clone)]
+pub struct ReleaseCommits {
+    /// The version bump, which is tagged
+    pub release: CommitTemplate,
+    pub changelog: CommitTemplate,
+    /// The bump to the next development version
+    pub development: CommitTemplate,
+    /// Make the version bump and the changelog a single commit
+    pub squash: bool,
+}
+
+impl ReleaseCommits {
+    /// The commit messages from the config, packages of a monorepo mention
+    /// the tag instead of the version by default.
+    pub fn from_config(config: &CommitsConfig, is_package: bool) -> Self {
+        let placeholder = if is_package {
+            TAG_PLACEHOLDER
+        } else {
+            VERSION_PLACEHOLDER
+        };
+        let template = |template: &Option<String>, default: &str| match template {
+            Some(template) => CommitTemplate::new(template),
+            None => CommitTemplate::new(&default.replace(VERSION_PLACEHOLDER, placeholder)),
+        };
+
+        ReleaseCommits {
+            release: template(&config.release, "chore: releasing {version}"),
+            changelog: template(&config.changelog, "docs: updating changelog [ci skip]"),
+            development: template(
+                &config.development,
+                "chore: beginning development on {version} [ci skip]",
+            ),
+            squash: config.squash,
+        }
+    }
+
+    /// Matches the subjects of all release commits, which are left out of changelogs.
+    pub fn subject_patterns(&self) -> Result<Vec<Regex>> {
+        [&self.release, &self.changelog, &self.development]
+            .iter()
+            .map(|template| template.subject_pattern())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let version = Version::parse("1.4.0").unwrap();
+        let template = CommitTemplate::new("release({name}): {version} ({tag})");
+
+        assert_eq!(
+            template.format("pkg-a", &version, "pkg-a@1.4.0"),
+            "release(pkg-a): 1.4.0 (pkg-a@1.4.0)"
+        );
+    }
+
+    #[test]
+    fn test_subject_pattern() {
+        let pattern = CommitTemplate::new("chore(release): {tag} [skip ci]\n\nBody")
+            .subject_pattern()
+            .unwrap();
+
+        assert!(pattern.is_match("chore(release): v1.4.0 [skip ci]"));
+        assert!(!pattern.is_match("chore(release): v1.4.0"));
+        assert!(!pattern.is_match("fix: chore(release): v1.4.0 [skip ci]"));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let version = Version::parse("1.4.0").unwrap();
+
+        let root = ReleaseCommits::from_config(&CommitsConfig::default(), false);
+        assert_eq!(
+            root.release.format("", &version, "v1.4.0"),
+            "chore: releasing 1.4.0"
+        );
+
+        let package = ReleaseCommits::from_config(&CommitsConfig::default(), true);
+        assert_eq!(
+            package.release.format("pkg-a", &version, "pkg-a@1.4.0"),
+            "chore: releasing pkg-a@1.4.0"
+        );
+
+        let config = CommitsConfig {
+            changelog: Some("docs: changelog for {tag}".to_string()),
+            ..CommitsConfig::default()
+        };
+        let custom = ReleaseCommits::from_config(&config, false);
+        assert_eq!(
+            custom.changelog.format("", &version, "v1.4.0"),
+            "docs: changelog for v1.4.0"
+        );
+        assert_eq!(custom.subject_patterns().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,339 @@
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+pub static CONFIG_FILE_PATH: &str = ".git-releaser.toml";
+
+/// Settings read from `.git-releaser.toml` in the root of the repository.
+///
+/// ## Example
+///
+/// ```toml
+/// [[packages]]
+/// name = "pkg-a"
+/// path = "packages/pkg-a"
+/// file = "package.json"
+/// tag_template = "{name}-v{version}"
+/// ```
+///
+/// Tags of a repository without packages follow the top level `tag_template`,
+/// e.g. `tag_template = "release-{version}"`.
+///
+/// ```toml
+/// [commits]
+/// release = "chore(release): {tag}"
+/// squash = true
+///
+/// [development]
+/// identifier = "dev"
+/// bump = "minor"
+///
+/// [changelog]
+/// entry_template = ".github/changelog-entry.md.j2"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Independently versioned packages of a monorepo
+    pub packages: Vec<PackageConfig>,
+    /// Tag names of the repository release, `v{version}` when unset
+    pub tag_template: Option<String>,
+    /// Release the packages depending on a released package with a patch bump
+    pub bump_dependents: bool,
+    pub commits: CommitsConfig,
+    pub development: DevelopmentConfig,
+    pub hooks: HooksConfig,
+    pub changelog: ChangelogConfig,
+}
+
+/// How the changelog is written and the MiniJinja templates it is rendered with,
+/// the built-in ones when unset.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangelogConfig {
+    pub format: ChangelogFormat,
+    /// The entry added to CHANGELOG.md, also the message of annotated tags
+    pub entry_template: Option<String>,
+    /// The body of GitHub releases and release pull requests
+    pub release_template: Option<String>,
+    /// The changes printed after a release
+    pub summary_template: Option<String>,
+    /// strftime format of the entry dates, `%Y-%m-%d` by default
+    pub date_format: Option<String>,
+    pub timezone: ChangelogTimezone,
+    /// The date of the entries, from `--date`
+    #[serde(skip)]
+    pub date: Option<String>,
+    /// Commits matching any of these rules are left out
+    pub exclude: Vec<CommitRuleConfig>,
+    /// Only commits matching any of these rules are listed, all commits when empty
+    pub include: Vec<CommitRuleConfig>,
+}
+
+/// Matches commits for the changelog, a rule matches when all of its fields do.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommitRuleConfig {
+    /// Regex of the subject
+    pub subject: Option<String>,
+    /// Regex of the author name or email
+    pub author: Option<String>,
+    /// Conventional commit type, e.g. `chore`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Regex of a trailer, e.g. `^Changelog: skip$`
+    pub trailer: Option<String>,
+    /// Matches commits only changing files in these directories
+    pub paths: Vec<String>,
+    /// Matches merge commits, or other commits when false
+    pub merge: Option<bool>,
+}
+
+/// The timezone the dates of changelog entries are in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogTimezone {
+    #[default]
+    Local,
+    Utc,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogFormat {
+    /// New entries go right below the `# CHANGELOG` header
+    #[default]
+    Default,
+    /// <https://keepachangelog.com>, promoting the `## [Unreleased]` section
+    KeepAChangelog,
+}
+
+/// Shell commands run during a release, a failing command aborts the release
+/// and rolls back everything that has not been pushed yet.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run before the version bump is committed
+    pub pre_commit: Vec<String>,
+    /// Run after the release commit is tagged
+    pub post_tag: Vec<String>,
+    /// Run after the GitHub release is created, when it can't be rolled back anymore
+    pub post_release: Vec<String>,
+    /// Files changed by the hooks to include in the release commit
+    pub stage: Vec<String>,
+}
+
+/// Messages of the release commits, with `{version}`, `{tag}` and `{name}` placeholders.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommitsConfig {
+    pub release: Option<String>,
+    pub changelog: Option<String>,
+    pub development: Option<String>,
+    /// Commit the version bump and the changelog together
+    pub squash: bool,
+}
+
+/// The version development continues on after a release.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevelopmentConfig {
+    /// Bump to a development version after releasing
+    pub enabled: bool,
+    /// Prerelease identifier, `SNAPSHOT` for Maven and `0` for other version files by default
+    pub identifier: Option<String>,
+    /// Which part of the released version to bump, `patch`, `minor` or `major`
+    pub bump: String,
+}
+
+impl Default for DevelopmentConfig {
+    fn default() -> Self {
+        DevelopmentConfig {
+            enabled: true,
+            identifier: None,
+            bump: "patch".to_string(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            packages: vec![],
+            tag_template: None,
+            bump_dependents: true,
+            commits: CommitsConfig::default(),
+            development: DevelopmentConfig::default(),
+            hooks: HooksConfig::default(),
+            changelog: ChangelogConfig::default(),
+        }
+    }
+}
+
+/// Where a version is stored and how to read it.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct VersionFileConfig {
+    pub file: String,
+    pub pattern: Option<String>,
+    pub key: Option<String>,
+    #[serde(default)]
+    pub version_code: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PackageConfig {
+    pub name: String,
+    /// Directory of the package, relative to the repository root
+    pub path: String,
+    /// Version file, relative to the package directory
+    #[serde(flatten)]
+    pub version_file: VersionFileConfig,
+    #[serde(default = "default_package_tag_template")]
+    pub tag_template: String,
+}
+
+fn default_package_tag_template() -> String {
+    "{name}@{version}".to_string()
+}
+
+impl Config {
+    /// Reads the config file, a missing file results in the default config.
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            debug!("No config found at {}", path);
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        Config::parse(&contents).wrap_err_with(|| format!("Invalid config in {}", path))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(toml_edit::easy::from_str(contents)?)
+    }
+
+    pub fn package(&self, name: &str) -> Result<&PackageConfig> {
+        self.packages
+            .iter()
+            .find(|package| package.name == name)
+            .ok_or_else(|| eyre!("No package named '{}' in {}", name, CONFIG_FILE_PATH))
+    }
+}
+
+impl PackageConfig {
+    /// The version file config with the file path relative to the repository root.
+    pub fn version_file_config(&self) -> VersionFileConfig {
+        let file = Path::new(&self.path).join(&self.version_file.file);
+        VersionFileConfig {
+            file: file.to_string_lossy().to_string(),
+            ..self.version_file.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+[[packages]]
+name = "pkg-a"
+path = "packages/pkg-a"
+file = "package.json"
+
+[[packages]]
+name = "pkg-b"
+path = "crates/pkg-b"
+file = "Cargo.toml"
+tag_template = "{name}-v{version}"
+"#,
+        )
+        .unwrap();
+
+        let pkg_a = config.package("pkg-a").unwrap();
+        assert_eq!(pkg_a.tag_template, "{name}@{version}");
+        assert_eq!(
+            pkg_a.version_file_config().file,
+            "packages/pkg-a/package.json"
+        );
+
+        assert!(!config.commits.squash);
+        assert!(config.development.enabled);
+
+        let pkg_b = config.package("pkg-b").unwrap();
+        assert_eq!(pkg_b.tag_template, "{name}-v{version}");
+        assert!(config.package("pkg-c").is_err());
+    }
+
+    #[test]
+    fn test_parse_release_settings() {
+        let config = Config::parse(
+            r#"
+[commits]
+release = "chore(release): {tag}"
+squash = true
+
+[development]
+identifier = "dev"
+bump = "minor"
+
+[changelog]
+format = "keep-a-changelog"
+entry_template = "changelog.md.j2"
+timezone = "utc"
+exclude = [{ merge = true }, { author = "dependabot\\[bot\\]", type = "chore" }]
+
+[[changelog.include]]
+paths = ["src"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.commits.release.unwrap(), "chore(release): {tag}");
+        assert_eq!(config.commits.changelog, None);
+        assert!(config.commits.squash);
+        assert!(config.development.enabled);
+        assert_eq!(config.development.identifier.unwrap(), "dev");
+        assert_eq!(config.development.bump, "minor");
+        assert!(config.hooks.pre_commit.is_empty());
+        assert_eq!(
+            config.changelog.entry_template.as_deref(),
+            Some("changelog.md.j2")
+        );
+        assert_eq!(config.changelog.summary_template, None);
+        assert_eq!(config.changelog.format, ChangelogFormat::KeepAChangelog);
+        assert_eq!(config.changelog.timezone, ChangelogTimezone::Utc);
+        assert_eq!(config.changelog.exclude.len(), 2);
+        assert_eq!(config.changelog.exclude[0].merge, Some(true));
+        assert_eq!(config.changelog.exclude[1].kind.as_deref(), Some("chore"));
+        assert_eq!(config.changelog.include[0].paths, vec!["src"]);
+    }
+
+    #[test]
+    fn test_parse_config_invalid() {
+        assert!(Config::parse("").unwrap().packages.is_empty());
+        assert!(Config::parse("").unwrap().bump_dependents);
+        assert_eq!(
+            Config::parse("tag_template = \"release-{version}\"")
+                .unwrap()
+                .tag_template
+                .unwrap(),
+            "release-{version}"
+        );
+        assert!(
+            !Config::parse("bump_dependents = false")
+                .unwrap()
+                .bump_dependents
+        );
+        assert!(Config::parse("[[packages]]\nname = \"pkg-a\"").is_err());
+        assert!(Config::parse("[commits]\nsquash = \"yes\"").is_err());
+        assert!(Config::parse("unknown = true").is_err());
+        assert!(Config::parse("[changelog]\nformat = \"plain\"").is_err());
+        assert!(Config::parse("[changelog]\ndate = \"2020-10-04\"").is_err());
+        assert!(Config::parse("[[changelog.exclude]]\nbranch = \"main\"").is_err());
+    }
+}
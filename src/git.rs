@@ -1,8 +1,138 @@
 use crate::commit::Commit;
 use eyre::Result;
+use std::collections::HashMap;
 use std::iter::FromIterator;
 use std::process::{Command, Output};
 
+/// Git config overrides for the commits and tags of a release,
+/// the ambient git config is used for anything not set.
+#[derive(Debug, Default, Clone)]
+pub struct GitConfig {
+    /// Signature format, `gpg` (the git default), `ssh` or `x509`
+    pub signing_format: Option<String>,
+    /// Key to sign with instead of `user.signingkey`
+    pub signing_key: Option<String>,
+    /// Author, committer and tagger name, e.g. for a bot in CI
+    pub user_name: Option<String>,
+    pub user_email: Option<String>,
+}
+
+impl GitConfig {
+    /// `-c` arguments that go before the git subcommand.
+    fn args(&self) -> Vec<String> {
+        [
+            ("gpg.format", &self.signing_format),
+            ("user.signingkey", &self.signing_key),
+            ("user.name", &self.user_name),
+            ("user.email", &self.user_email),
+        ]
+        .iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+        .flat_map(|config| ["-c".to_string(), config])
+        .collect()
+    }
+}
+
+/// How release tags are created, lightweight tags by default.
+#[derive(Debug, Default, Clone)]
+pub struct TagOptions {
+    /// Create an annotated tag with the changelog entry as its message
+    pub annotate: bool,
+    /// Sign the tag, which makes it annotated as well
+    pub sign: bool,
+    pub config: GitConfig,
+}
+
+impl TagOptions {
+    fn tag_args(&self, new_tag: &str, message: &str) -> Vec<String> {
+        let mut args = self.config.args();
+        args.push("tag".to_string());
+        if self.sign {
+            args.push("-s".to_string());
+        } else if self.annotate {
+            args.push("-a".to_string());
+        } else {
+            args.push(new_tag.to_string());
+            return args;
+        }
+        // Keep the markdown headings, which would be stripped as comments by default
+        args.push("--cleanup=verbatim".to_string());
+        args.extend(["-m".to_string(), message.to_string(), new_tag.to_string()]);
+        args
+    }
+}
+
+/// Where and how releases are pushed.
+#[derive(Debug, Clone)]
+pub struct PushOptions {
+    /// Remotes to push to, the first one is also fetched from
+    pub remotes: Vec<String>,
+    /// Push the branch and the tag in a single atomic push
+    pub atomic: bool,
+}
+
+impl Default for PushOptions {
+    fn default() -> Self {
+        PushOptions {
+            remotes: vec!["origin".to_string()],
+            atomic: false,
+        }
+    }
+}
+
+impl PushOptions {
+    fn push_args(&self, refs: &[&str]) -> Vec<Vec<String>> {
+        let push = |remote: &str, refs: &[&str]| {
+            let mut args = vec!["push".to_string()];
+            if self.atomic {
+                args.push("--atomic".to_string());
+            }
+            args.push(remote.to_string());
+            args.extend(refs.iter().map(|r| r.to_string()));
+            args
+        };
+
+        self.remotes
+            .iter()
+            .flat_map(|remote| {
+                if self.atomic {
+                    vec![push(remote, refs)]
+                } else {
+                    refs.iter().map(|r| push(remote, &[r])).collect()
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the release commits are created.
+#[derive(Debug, Default, Clone)]
+pub struct CommitOptions {
+    /// Sign the commits, needed for branches that only accept signed commits
+    pub sign: bool,
+    /// Trailers added to every commit, e.g. `Signed-off-by: Release Bot <bot@example.com>`
+    pub trailers: Vec<String>,
+    pub config: GitConfig,
+}
+
+impl CommitOptions {
+    fn commit_args(&self, commit_message: &str) -> Vec<String> {
+        let mut args = self.config.args();
+        args.extend([
+            "commit".to_string(),
+            "-m".to_string(),
+            commit_message.to_string(),
+        ]);
+        if self.sign {
+            args.push("-S".to_string());
+        }
+        for trailer in &self.trailers {
+            args.extend(["--trailer".to_string(), trailer.to_owned()]);
+        }
+        args
+    }
+}
+
 /// Check if we're in an git repository.
 pub fn in_git_repository() -> Result<bool> {
     git(&["rev-parse", "--is-inside-work-tree"]).map(|o| o.status.success())
@@ -24,9 +154,33 @@ pub fn first_commit() -> Result<String> {
     }
 }
 
-/// Get the last tag
-pub fn last_tag() -> Result<Option<String>> {
-    last_tags(1).map(|mut v| v.pop())
+/// Get the tags reachable from the branch
+pub fn tags_merged_into(branch: &str) -> Result<Vec<String>> {
+    git(&["tag", "--merged", branch]).map(|o| read_lines(&o))
+}
+
+/// The commit time of a ref in seconds since the Unix epoch.
+pub fn commit_timestamp(git_ref: &str) -> Result<i64> {
+    let args = vec!["log", "-1", "--format=%ct", git_ref];
+    let timestamp: String = git(&args).map(|o| read_lines(&o))?;
+    timestamp
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("Could not read the commit time of {}", git_ref))
+}
+
+/// Check if the tag exists
+pub fn tag_exists(tag: &str) -> Result<bool> {
+    let tags: Vec<String> = git(&["tag", "--list", tag]).map(|o| read_lines(&o))?;
+    Ok(!tags.is_empty())
+}
+
+/// Check if the tag exists on the remote, including tags that were never fetched
+pub fn remote_tag_exists(tag: &str, remote: &str) -> Result<bool> {
+    let tag_ref = format!("refs/tags/{}", tag);
+    let refs: Vec<String> =
+        git(&["ls-remote", "--tags", remote, &tag_ref]).map(|o| read_lines(&o))?;
+    Ok(!refs.is_empty())
 }
 
 /// Returns a list of commits for the specified project
@@ -36,51 +190,59 @@ pub fn last_tag() -> Result<Option<String>> {
 /// ```rust
 /// let range = "<commit a>..<commit b>".to_string();
 /// let commits: Vec<String> = commits_in_log(&[range])?;
+///
+/// // Only commits touching a directory
+/// let commits: Vec<String> = commits_in_log(&[range, "--".to_string(), "packages/pkg-a".to_string()])?;
 /// ```
 pub fn commits_in_log(args: &[String]) -> Result<Vec<Commit>> {
     // I hope this can be improved a bit, its a bit messy and hard to understand
     // but it is just taking selected info using the git log formatter and creating
     // a json object that gets parsed into a Commit struct.
-    let mut log_args = vec!["log", "--pretty=format:{ \"commit\": \"%H\", \"abbreviated_commit\": \"%h\", \"refs\": \"%D\", \"subject\": \"%s\", \"sanitized_subject_line\": \"%f\", \"commit_notes\": \"\", \"author\": { \"name\": \"%aN\", \"email\": \"%aE\", \"date\": \"%aD\" }, \"committer\": { \"name\": \"%cN\", \"email\": \"%cE\", \"date\": \"%cD\" }}END"];
+    let mut log_args = vec!["log", "--pretty=format:{ \"commit\": \"%H\", \"abbreviated_commit\": \"%h\", \"refs\": \"%D\", \"subject\": \"%s\", \"sanitized_subject_line\": \"%f\", \"commit_notes\": \"\", \"author\": { \"name\": \"%aN\", \"email\": \"%aE\", \"date\": \"%aD\" }, \"committer\": { \"name\": \"%cN\", \"email\": \"%cE\", \"date\": \"%cD\" }, \"parents\": \"%P\"}END"];
     log_args.extend(args.iter().map(String::as_str));
-    git(&log_args).map(|o| {
+    let mut commits = git(&log_args).map(|o| {
         let all_lines: String = read_lines(&o);
         // Split by END which is my end of commit indicator
         all_lines
             .split("END")
             .filter(|e| e.len() > 2)
             .map(Commit::from)
-            .filter(|commit| {
-                // filter out the following:
-                // - `chore: releasing vX.Y.Z`
-                // - `chore: beginning development on vX.Y.Z`
-                // - `docs: updating changelog`
-
-                let subj = commit.subject.to_string();
-                if subj.starts_with("chore: releasing")
-                    || subj.starts_with("chore: beginning development on")
-                    || subj.starts_with("docs: updating changelog")
-                {
-                    return false;
-                }
-                true
-            })
             .collect::<Vec<Commit>>()
-    })
+    })?;
+
+    // The bodies can't be put in the json above, so they are read separately
+    let mut body_args = vec!["log", "--format=%H%x1f%b%x1e"];
+    body_args.extend(args.iter().map(String::as_str));
+    let output = git(&body_args)?;
+    let bodies = String::from_utf8_lossy(&output.stdout);
+    let mut notes = bodies
+        .split('\x1e')
+        .filter_map(|entry| entry.trim_start().split_once('\x1f'))
+        .collect::<HashMap<_, _>>();
+    for commit in &mut commits {
+        if let Some(body) = notes.remove(commit.commit.as_str()) {
+            commit.commit_notes = body.trim().to_string();
+        }
+    }
+
+    Ok(commits)
+}
+
+/// The trailers of a commit, e.g. `Signed-off-by: Jane <jane@example.com>`.
+pub fn commit_trailers(commit: &str) -> Result<Vec<String>> {
+    let args = vec!["log", "-1", "--format=%(trailers:only,unfold)", commit];
+    let trailers: Vec<String> = git(&args).map(|o| read_lines(&o))?;
+    Ok(trailers
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .collect())
 }
 
-// https://stackoverflow.com/questions/18659959/git-tag-sorted-in-chronological-order-of-the-date-of-the-commit-pointed-to/57901182#comment75323431_36636526
-/// Get the last n tags
-fn last_tags(n: i32) -> Result<Vec<String>> {
-    git(&[
-        "for-each-ref",
-        &format!("--count={}", n),
-        // "--sort=-taggerdate",
-        "--sort=-committerdate",
-        "--format=%(refname:short)",
-        "refs/tags/*",
-    ])
-    .map(|o| read_lines(&o))
+/// The files changed by a commit.
+pub fn changed_files(commit: &str) -> Result<Vec<String>> {
+    let args = vec!["show", "--name-only", "--format=", commit];
+    let files: Vec<String> = git(&args).map(|o| read_lines(&o))?;
+    Ok(files.into_iter().filter(|file| !file.is_empty()).collect())
 }
 
 /// Stages the specified files.
@@ -92,33 +254,112 @@ pub fn add_files(files: Vec<String>) -> Result<Output> {
 
 /// Commits staged files with the given message.
 /// Errors if there are no staged files.
-pub fn commit(commit_message: &str) -> Result<bool> {
-    let args = vec!["commit", "-m", commit_message];
+pub fn commit(commit_message: &str, options: &CommitOptions) -> Result<bool> {
+    if git(&["diff", "--cached", "--quiet"]).is_ok() {
+        return Err(eyre!("\nNo staged changes detected. Exiting."));
+    }
 
-    if git(&args).is_err() {
-        Err(eyre!("\nNo staged changes detected. Exiting."))
-    } else {
-        Ok(true)
+    let args = options.commit_args(commit_message);
+    git(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+    Ok(true)
+}
+
+/// Creates the tag, the message is only used for annotated and signed tags.
+pub fn tag(new_tag: &str, message: &str, options: &TagOptions) -> Result<Output> {
+    let args = options.tag_args(new_tag, message);
+    git(&args.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+/// Tags the given commit instead of HEAD.
+pub fn tag_commit(
+    new_tag: &str,
+    commit: &str,
+    message: &str,
+    options: &TagOptions,
+) -> Result<Output> {
+    let mut args = options.tag_args(new_tag, message);
+    args.push(commit.to_string());
+    git(&args.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+/// Verifies the signature of a signed tag.
+pub fn verify_tag(tag: &str, options: &TagOptions) -> Result<Output> {
+    let mut args = options.config.args();
+    args.extend(["tag".to_string(), "-v".to_string(), tag.to_string()]);
+    git(&args.iter().map(String::as_str).collect::<Vec<_>>())
+        .map_err(|e| eyre!("The signature of {} could not be verified: {}", tag, e))
+}
+
+/// Returns the commit HEAD points to.
+pub fn head() -> Result<String> {
+    git(&["rev-parse", "HEAD"]).map(|o| read_lines(&o))
+}
+
+/// Lists the uncommitted changes to tracked files, empty when the working tree is clean.
+pub fn uncommitted_changes() -> Result<Vec<String>> {
+    git(&["status", "--porcelain", "--untracked-files=no"]).map(|o| read_lines(&o))
+}
+
+/// Resets the branch to the commit, keeping local changes to files the commits after it didn't touch.
+pub fn reset_keep(commit: &str) -> Result<Output> {
+    git(&["reset", "--keep", commit])
+}
+
+/// Restores the files to their contents in the commit, skipping files the commit doesn't have.
+pub fn restore_files(commit: &str, files: &[String]) -> Result<()> {
+    let mut args = vec!["ls-tree", "-r", "--name-only", commit, "--"];
+    args.extend(files.iter().map(String::as_str));
+    let existing: Vec<String> = git(&args).map(|o| read_lines(&o))?;
+    if existing.is_empty() {
+        return Ok(());
     }
+
+    let mut args = vec!["checkout", commit, "--"];
+    args.extend(existing.iter().map(String::as_str));
+    git(&args)?;
+    Ok(())
+}
+
+pub fn delete_tag(tag: &str) -> Result<Output> {
+    git(&["tag", "-d", tag])
+}
+
+pub fn delete_branch(branch: &str) -> Result<Output> {
+    git(&["branch", "-D", branch])
+}
+
+/// Switches to the branch.
+pub fn checkout(branch: &str) -> Result<Output> {
+    git(&["checkout", branch])
 }
 
-pub fn tag(new_tag: &str) -> Result<Output> {
-    let args = vec!["tag", new_tag];
-    git(&args)
+/// Creates the branch from HEAD and switches to it.
+pub fn checkout_new_branch(branch: &str) -> Result<Output> {
+    git(&["checkout", "-b", branch])
 }
 
-/// Pushes everything committed to the main upstream
-/// i.e. just `git push`
-pub fn push(main_branch: &str) -> Result<Output> {
-    let push_args = vec!["push", "origin", main_branch];
-    git(&push_args)
+/// Creates the branch from the start point and switches to it.
+pub fn checkout_new_branch_from(branch: &str, start_point: &str) -> Result<Output> {
+    git(&["checkout", "-b", branch, start_point])
 }
 
-/// Pushes the new tag to the main upstream
-/// i.e. `git push origin TAG`
-pub fn push_tag(tag_ver: &str) -> Result<Output> {
-    let tag_args = vec!["push", "origin", tag_ver];
-    git(&tag_args)
+/// Fetches the branch from the first remote.
+pub fn fetch(branch: &str, options: &PushOptions) -> Result<Output> {
+    let remote = options
+        .remotes
+        .first()
+        .map(String::as_str)
+        .unwrap_or("origin");
+    git(&["fetch", remote, branch])
+}
+
+/// Pushes branches and tags to every remote
+/// i.e. `git push origin main` and `git push origin TAG`
+pub fn push(refs: &[&str], options: &PushOptions) -> Result<()> {
+    for args in options.push_args(refs) {
+        git(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+    }
+    Ok(())
 }
 
 /// Returns the corresponding commit for a tag
@@ -127,10 +368,47 @@ pub fn get_commit_for_tag(tag_ver: &str) -> Result<String> {
     git(&args).map(|o| read_lines(&o))
 }
 
+#[cfg(test)]
+thread_local! {
+    static TEST_REPO: std::cell::RefCell<Option<std::path::PathBuf>> = Default::default();
+}
+
+/// Creates a repository with an empty first commit and runs the git commands
+/// of the calling test in it until `leave_test_repo` is called.
+#[cfg(test)]
+pub fn enter_test_repo(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("git-releaser-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    TEST_REPO.with(|repo| *repo.borrow_mut() = Some(dir.to_owned()));
+
+    git(&["init", "-q"]).unwrap();
+    git(&["config", "user.name", "test"]).unwrap();
+    git(&["config", "user.email", "test@example.com"]).unwrap();
+    git(&["config", "commit.gpgsign", "false"]).unwrap();
+    git(&["config", "tag.gpgsign", "false"]).unwrap();
+    git(&["commit", "-q", "--allow-empty", "-m", "init"]).unwrap();
+    dir
+}
+
+#[cfg(test)]
+pub fn leave_test_repo() {
+    if let Some(dir) = TEST_REPO.with(|repo| repo.borrow_mut().take()) {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
 /// Run a git command with arguments.
 fn git(args: &[&str]) -> Result<Output> {
     debug!("git {}", args.join(" "));
-    let output = Command::new("git").args(args).output()?;
+    let mut command = Command::new("git");
+    #[cfg(test)]
+    TEST_REPO.with(|repo| {
+        if let Some(dir) = &*repo.borrow() {
+            command.current_dir(dir);
+        }
+    });
+    let output = command.args(args).output()?;
     if output.status.success() {
         Ok(output)
     } else {
@@ -161,14 +439,174 @@ mod tests {
         assert!(in_git_repository().unwrap());
     }
 
+    #[test]
+    fn test_head() {
+        assert_eq!(head().unwrap().len(), 40);
+    }
+
     #[test]
     fn test_first_commit() {
         assert!(first_commit().is_ok());
     }
 
     #[test]
-    fn test_last_tag() {
-        assert!(last_tag().is_ok());
+    fn test_tag_args() {
+        let mut options = TagOptions::default();
+        assert_eq!(options.tag_args("v1.0.0", "notes"), vec!["tag", "v1.0.0"]);
+
+        options.annotate = true;
+        assert_eq!(
+            options.tag_args("v1.0.0", "notes"),
+            vec!["tag", "-a", "--cleanup=verbatim", "-m", "notes", "v1.0.0"]
+        );
+
+        options.sign = true;
+        assert_eq!(
+            options.tag_args("v1.0.0", "notes"),
+            vec!["tag", "-s", "--cleanup=verbatim", "-m", "notes", "v1.0.0"]
+        );
+
+        options.config.signing_format = Some("ssh".to_string());
+        options.config.signing_key = Some("~/.ssh/id_ed25519.pub".to_string());
+        assert_eq!(
+            options.tag_args("v1.0.0", "notes"),
+            vec![
+                "-c",
+                "gpg.format=ssh",
+                "-c",
+                "user.signingkey=~/.ssh/id_ed25519.pub",
+                "tag",
+                "-s",
+                "--cleanup=verbatim",
+                "-m",
+                "notes",
+                "v1.0.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_args() {
+        let mut options = PushOptions::default();
+        assert_eq!(
+            options.push_args(&["main", "v1.0.0"]),
+            vec![
+                vec!["push", "origin", "main"],
+                vec!["push", "origin", "v1.0.0"]
+            ]
+        );
+
+        options.atomic = true;
+        options.remotes = vec!["upstream".to_string(), "mirror".to_string()];
+        assert_eq!(
+            options.push_args(&["main", "v1.0.0"]),
+            vec![
+                vec!["push", "--atomic", "upstream", "main", "v1.0.0"],
+                vec!["push", "--atomic", "mirror", "main", "v1.0.0"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_args() {
+        let mut options = CommitOptions::default();
+        assert_eq!(
+            options.commit_args("chore: releasing 1.0.0"),
+            vec!["commit", "-m", "chore: releasing 1.0.0"]
+        );
+
+        options.sign = true;
+        options.trailers = vec!["Signed-off-by: Bot <bot@example.com>".to_string()];
+        options.config.user_name = Some("Bot".to_string());
+        options.config.user_email = Some("bot@example.com".to_string());
+        assert_eq!(
+            options.commit_args("chore: releasing 1.0.0"),
+            vec![
+                "-c",
+                "user.name=Bot",
+                "-c",
+                "user.email=bot@example.com",
+                "commit",
+                "-m",
+                "chore: releasing 1.0.0",
+                "-S",
+                "--trailer",
+                "Signed-off-by: Bot <bot@example.com>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_exists() {
+        assert!(!tag_exists("no-such-tag").unwrap());
+    }
+
+    #[test]
+    fn test_commit_timestamp() {
+        assert!(commit_timestamp("HEAD").unwrap() > 0);
+        assert!(commit_timestamp("no-such-tag").is_err());
+    }
+
+    #[test]
+    fn test_remote_tag_exists() {
+        let upstream = enter_test_repo("upstream-test");
+        git(&["tag", "v99.0.0-upstream"]).unwrap();
+        // Back in this repository, which doesn't have the tag
+        TEST_REPO.with(|repo| repo.borrow_mut().take());
+
+        let remote = upstream.to_string_lossy();
+        let released = remote_tag_exists("v99.0.0-upstream", &remote);
+        let unreleased = remote_tag_exists("v99.0.1-upstream", &remote);
+        std::fs::remove_dir_all(&upstream).unwrap();
+
+        assert!(!tag_exists("v99.0.0-upstream").unwrap());
+        assert!(released.unwrap());
+        assert!(!unreleased.unwrap());
+    }
+
+    #[test]
+    fn test_reset_keep() {
+        let dir = enter_test_repo("reset-keep-test");
+        let start = head().unwrap();
+        std::fs::write(dir.join("version.txt"), "1.0.0").unwrap();
+        std::fs::write(dir.join("notes.txt"), "todo").unwrap();
+        git(&["add", "."]).unwrap();
+        git(&["commit", "-q", "-m", "files"]).unwrap();
+        let files = head().unwrap();
+        std::fs::write(dir.join("version.txt"), "1.1.0").unwrap();
+        git(&["commit", "-q", "-am", "release"]).unwrap();
+        std::fs::write(dir.join("notes.txt"), "still todo").unwrap();
+
+        reset_keep(&files).unwrap();
+        let version = std::fs::read_to_string(dir.join("version.txt")).unwrap();
+        let notes = std::fs::read_to_string(dir.join("notes.txt")).unwrap();
+        let changes = uncommitted_changes().unwrap();
+
+        std::fs::write(dir.join("version.txt"), "2.0.0").unwrap();
+        let release_files = vec!["version.txt".to_string(), "CHANGELOG.md".to_string()];
+        restore_files(&files, &release_files).unwrap();
+        let restored = std::fs::read_to_string(dir.join("version.txt")).unwrap();
+        assert!(restore_files(&start, &release_files).is_ok());
+        leave_test_repo();
+
+        assert_eq!(version, "1.0.0");
+        assert_eq!(notes, "still todo");
+        assert_eq!(changes, vec![" M notes.txt"]);
+        assert_eq!(restored, "1.0.0");
+    }
+
+    #[test]
+    fn test_commit_details() {
+        let head = head().unwrap();
+        assert!(commit_trailers(&head).is_ok());
+        assert!(changed_files(&head).is_ok());
+        assert!(changed_files("no-such-commit").is_err());
+    }
+
+    #[test]
+    fn test_tags_merged_into() {
+        assert!(tags_merged_into("HEAD").is_ok());
+        assert!(tags_merged_into("no-such-branch").is_err());
     }
 
     #[test]
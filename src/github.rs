@@ -1,14 +1,79 @@
 use eyre::Result;
-use hubcaps::{releases::ReleaseOptions, Credentials, Github};
+use hubcaps::{pulls::PullOptions, releases::ReleaseOptions, Credentials, Github};
+use serde::{Deserialize, Serialize};
 
 const AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const API_URL: &str = "https://api.github.com";
+const WEB_URL: &str = "https://github.com";
 
 pub struct GithubClient {
     client: Github,
+    token: String,
     owner: String,
     repo: String,
 }
 
+/// Release options with `make_latest`, which hubcaps does not support yet.
+#[derive(Serialize)]
+struct NonLatestRelease<'a> {
+    #[serde(flatten)]
+    options: &'a ReleaseOptions,
+    make_latest: &'static str,
+}
+
+/// A merged pull request, the fields of a pull request hubcaps can't parse
+/// from the list endpoint.
+#[derive(Debug, Deserialize)]
+pub struct MergedPullRequest {
+    pub number: u64,
+    pub body: Option<String>,
+    pub merged_at: Option<String>,
+    pub merge_commit_sha: Option<String>,
+}
+
+/// Links to the web pages of a repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryLinks {
+    url: String,
+}
+
+impl RepositoryLinks {
+    pub fn new(url: &str) -> Self {
+        RepositoryLinks {
+            url: url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Links to a GitHub project, e.g. `owner/repo`.
+    pub fn for_project(project: &str) -> Self {
+        RepositoryLinks::new(&format!("{}/{}", WEB_URL, project))
+    }
+
+    /// The repository itself, e.g. `https://github.com/owner/repo`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn commit(&self, hash: &str) -> String {
+        format!("{}/commit/{}", self.url, hash)
+    }
+
+    /// An issue or pull request, GitHub redirects issue links of pull requests.
+    pub fn issue(&self, number: &str) -> String {
+        format!("{}/issues/{}", self.url, number)
+    }
+
+    /// The GitHub release of a tag.
+    pub fn release(&self, tag: &str) -> String {
+        format!("{}/releases/tag/{}", self.url, tag)
+    }
+
+    /// The changes between two refs.
+    pub fn compare(&self, from: &str, to: &str) -> String {
+        format!("{}/compare/{}...{}", self.url, from, to)
+    }
+}
+
 impl GithubClient {
     pub fn new<T>(project: T, pat: T) -> Result<Self>
     where
@@ -19,16 +84,20 @@ impl GithubClient {
 
         Ok(Self {
             client,
+            token: pat.to_string(),
             owner,
             repo,
         })
     }
 
+    /// Creates a release, `make_latest` is false for releases of older versions
+    /// that should not replace the latest release of the repository.
     pub async fn create_new_release<T>(
         &self,
         tag: T,
         tagged_commitish: T,
         changelog: T,
+        make_latest: bool,
     ) -> Result<bool>
     where
         T: ToString,
@@ -43,6 +112,26 @@ impl GithubClient {
             prerelease: Some(false),
         };
 
+        if !make_latest {
+            let release = NonLatestRelease {
+                options: &release_opts,
+                make_latest: "false",
+            };
+            reqwest::Client::new()
+                .post(&format!(
+                    "{}/repos/{}/{}/releases",
+                    API_URL, self.owner, self.repo
+                ))
+                .header("User-Agent", AGENT)
+                .header("Authorization", format!("token {}", self.token))
+                .body(serde_json::to_vec(&release)?)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            return Ok(true);
+        }
+
         client
             .repo(&self.owner, &self.repo)
             .releases()
@@ -51,6 +140,47 @@ impl GithubClient {
 
         Ok(true)
     }
+
+    /// Opens a pull request and returns its URL.
+    pub async fn create_pull_request(
+        &self,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<String> {
+        let pull = self
+            .client
+            .repo(&self.owner, &self.repo)
+            .pulls()
+            .create(&PullOptions::new(title, head, base, Some(body)))
+            .await?;
+
+        Ok(pull.html_url)
+    }
+
+    /// Finds the merged pull request of the branch, if any.
+    pub async fn merged_pull_request(&self, head: &str) -> Result<Option<MergedPullRequest>> {
+        let pulls: Vec<MergedPullRequest> = reqwest::Client::new()
+            .get(&format!(
+                "{}/repos/{}/{}/pulls",
+                API_URL, self.owner, self.repo
+            ))
+            .query(&[
+                ("state", "closed"),
+                ("head", &format!("{}:{}", self.owner, head)),
+            ])
+            .header("User-Agent", AGENT)
+            .header("Authorization", format!("token {}", self.token))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+            .map(|body| serde_json::from_slice(&body))??;
+
+        Ok(pulls.into_iter().find(|pull| pull.merged_at.is_some()))
+    }
 }
 
 /// Creates a tuple from the '<org>/<repo>' value
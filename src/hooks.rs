@@ -0,0 +1,78 @@
+use eyre::Result;
+use std::process::Command;
+
+/// Release details passed to the hook commands as environment variables.
+#[derive(Debug, Clone)]
+pub struct HookEnv {
+    pub version: String,
+    pub tag: String,
+    /// Empty for the first release
+    pub previous_version: Option<String>,
+    pub changelog_path: String,
+}
+
+impl HookEnv {
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("RELEASE_VERSION", self.version.to_owned()),
+            ("RELEASE_TAG", self.tag.to_owned()),
+            (
+                "PREVIOUS_VERSION",
+                self.previous_version.to_owned().unwrap_or_default(),
+            ),
+            ("CHANGELOG_PATH", self.changelog_path.to_owned()),
+        ]
+    }
+}
+
+/// Runs the commands of a hook with `sh`, stopping at the first one that fails.
+pub fn run_hooks(hook: &str, commands: &[String], env: &HookEnv) -> Result<()> {
+    for command in commands {
+        info!("🪝 Running {} hook: {}", hook, command);
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(env.vars())
+            .status()?;
+
+        if !status.success() {
+            return Err(eyre!("The {} hook `{}` failed, {}", hook, command, status));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> HookEnv {
+        HookEnv {
+            version: "1.4.0".to_string(),
+            tag: "v1.4.0".to_string(),
+            previous_version: None,
+            changelog_path: "CHANGELOG.md".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let commands = vec![
+            "test \"$RELEASE_VERSION\" = 1.4.0".to_string(),
+            "test \"$RELEASE_TAG\" = v1.4.0 && test -z \"$PREVIOUS_VERSION\"".to_string(),
+        ];
+        assert!(run_hooks("pre_commit", &commands, &env()).is_ok());
+    }
+
+    #[test]
+    fn test_run_hooks_failing() {
+        let commands = vec!["exit 1".to_string(), "touch should-not-run".to_string()];
+        let err = run_hooks("post_tag", &commands, &env()).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("The post_tag hook `exit 1` failed"));
+        assert!(!std::path::Path::new("should-not-run").exists());
+    }
+}
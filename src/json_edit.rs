@@ -0,0 +1,307 @@
+use eyre::Result;
+use std::ops::Range;
+
+/// Reads the string value found at `path`.
+/// Returns `None` when the path does not exist in the document.
+///
+/// Comments and trailing commas (JSONC) are accepted.
+pub fn read_string<S: AsRef<str>>(contents: &str, path: &[S]) -> Result<Option<String>> {
+    match find_value(contents, path)? {
+        Some(span) => parse_string(&contents[span]).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Replaces the string value found at `path` with `new_value`, leaving every
+/// other byte of the document untouched.
+pub fn replace_string<S: AsRef<str>>(
+    contents: &str,
+    path: &[S],
+    new_value: &str,
+) -> Result<String> {
+    let span = match find_value(contents, path)? {
+        Some(span) => span,
+        None => return Err(eyre!("No value found at '{}'", display_path(path))),
+    };
+    parse_string(&contents[span.to_owned()])?;
+
+    let mut updated_contents = contents.to_owned();
+    updated_contents.replace_range(span, &serde_json::to_string(new_value)?);
+
+    Ok(updated_contents)
+}
+
+/// Returns the byte range of the value found at `path`.
+/// Array elements are addressed by their index.
+pub fn find_value<S: AsRef<str>>(contents: &str, path: &[S]) -> Result<Option<Range<usize>>> {
+    let mut scanner = Scanner {
+        bytes: contents.as_bytes(),
+        pos: 0,
+    };
+    scanner.find(path)
+}
+
+fn display_path<S: AsRef<str>>(path: &[S]) -> String {
+    path.iter()
+        .map(|segment| segment.as_ref())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn parse_string(raw: &str) -> Result<String> {
+    serde_json::from_str::<String>(raw).map_err(|_| eyre!("Expected a string value, got {}", raw))
+}
+
+/// Bytes that can make up a number, `true`, `false` or `null`.
+fn is_literal_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'-' | b'+' | b'.')
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn find<S: AsRef<str>>(&mut self, path: &[S]) -> Result<Option<Range<usize>>> {
+        self.skip_whitespace()?;
+
+        let (segment, rest) = match path.split_first() {
+            Some(split) => split,
+            None => {
+                let start = self.pos;
+                self.skip_value()?;
+                return Ok(Some(start..self.pos));
+            }
+        };
+
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek() == Some(b'}') {
+                        return Ok(None);
+                    }
+                    let key = self.key()?;
+                    if key == segment.as_ref() {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(b'}')? {
+                        return Ok(None);
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let index = segment.as_ref().parse::<usize>().ok();
+                let mut i = 0;
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek() == Some(b']') {
+                        return Ok(None);
+                    }
+                    if index == Some(i) {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(b']')? {
+                        return Ok(None);
+                    }
+                    i += 1;
+                }
+            }
+            _ => {
+                self.skip_value()?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads an object key and the `:` that follows it.
+    fn key(&mut self) -> Result<String> {
+        let start = self.pos;
+        self.skip_string()?;
+        let key = std::str::from_utf8(&self.bytes[start..self.pos])?;
+        let key = parse_string(key)?;
+
+        self.skip_whitespace()?;
+        self.expect(b':')?;
+        Ok(key)
+    }
+
+    /// Consumes the separator after an object member or array element.
+    /// Returns false when the container has been closed.
+    fn next_item(&mut self, close: u8) -> Result<bool> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(c) if c == close => {
+                self.pos += 1;
+                Ok(false)
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some(b'"') => self.skip_string(),
+            Some(open @ (b'{' | b'[')) => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace()?;
+                    if self.peek() == Some(close) {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    if open == b'{' {
+                        self.key()?;
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(close)? {
+                        return Ok(());
+                    }
+                }
+            }
+            Some(c) if is_literal_byte(c) => {
+                while self.peek().is_some_and(is_literal_byte) {
+                    self.pos += 1;
+                }
+                Ok(())
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        self.expect(b'"')?;
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.pos += 2,
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(_) => self.pos += 1,
+                None => return Err(self.error()),
+            }
+        }
+    }
+
+    /// Skips whitespace as well as `//` and `/* */` comments.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match (self.peek(), self.bytes.get(self.pos + 1)) {
+                (Some(c), _) if c.is_ascii_whitespace() => self.pos += 1,
+                (Some(b'/'), Some(b'/')) => {
+                    while !matches!(self.peek(), Some(b'\n') | None) {
+                        self.pos += 1;
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    self.pos += 2;
+                    while !self.bytes[self.pos..].starts_with(b"*/") {
+                        if self.peek().is_none() {
+                            return Err(self.error());
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 2;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() != Some(c) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn error(&self) -> eyre::Report {
+        eyre!("Invalid JSON at byte {}", self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_string() {
+        let contents = r#"{
+  "name": "testing",
+  "scripts": { "test": "jest --version \"1.0.0\"" },
+  "version": "0.2.5"
+}"#;
+
+        assert_eq!(
+            read_string(contents, &["version"]).unwrap(),
+            Some("0.2.5".to_string())
+        );
+        assert_eq!(read_string(contents, &["missing"]).unwrap(), None);
+        assert!(read_string(contents, &["scripts"]).is_err());
+    }
+
+    #[test]
+    fn test_read_string_nested() {
+        let contents = r#"{ "expo": { "plugins": ["a", { "version": "1.0.0" }] } }"#;
+
+        assert_eq!(
+            read_string(contents, &["expo", "plugins", "1", "version"]).unwrap(),
+            Some("1.0.0".to_string())
+        );
+        assert_eq!(
+            read_string(contents, &["expo", "plugins", "2"]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_replace_string_preserves_formatting() {
+        let contents =
+            "{\n\t\"name\":  \"testing\",\n\t\"size\": 1.50e3,\n\t\"version\": \"0.2.5\"\n}";
+
+        let res = replace_string(contents, &["version"], "0.3.0").unwrap();
+        assert_eq!(
+            res,
+            "{\n\t\"name\":  \"testing\",\n\t\"size\": 1.50e3,\n\t\"version\": \"0.3.0\"\n}"
+        );
+    }
+
+    #[test]
+    fn test_replace_string_jsonc() {
+        let contents = r#"{
+    // The version of the module
+    "version": /* inline */ "0.2.5",
+    "tasks": {
+        "dev": "deno run main.ts", // trailing comma
+    },
+}
+"#;
+
+        let res = replace_string(contents, &["version"], "1.0.0").unwrap();
+        assert_eq!(res, contents.replace("\"0.2.5\"", "\"1.0.0\""));
+    }
+
+    #[test]
+    fn test_replace_string_invalid() {
+        assert!(replace_string(r#"{ "name": "testing" }"#, &["version"], "1.0.0").is_err());
+        assert!(replace_string(r#"{ "version": 1 }"#, &["version"], "1.0.0").is_err());
+        assert!(replace_string(r#"{ "version" "1.0.0" }"#, &["version"], "1.0.0").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::changelog_gen::ENTRY_HEADING;
+use eyre::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// The start of a new changelog in the Keep a Changelog format.
+pub static HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+## [Unreleased]
+";
+
+static UNRELEASED: &str = "## [Unreleased]";
+
+/// Matches the link definitions at the bottom of the changelog, e.g. `[1.3.0]: https://...`.
+static LINK_DEFINITION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\[([^\]]+)\]: .*$").unwrap());
+/// Where a section ends, at the next heading or the link definitions
+static SECTION_END: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(## |\[[^\]]+\]: )").unwrap());
+
+/// Promotes the Unreleased section to the released version. Notes written under
+/// Unreleased by hand become the notes of the release, the generated `entry` is
+/// used when there are none.
+pub fn promote_unreleased(contents: &str, version: &str, entry: &str) -> Result<String> {
+    if contents.contains(&format!("## [{}]", version)) {
+        return Err(eyre!("Version entry for {} already exists", version));
+    }
+    let contents = with_unreleased_section(contents);
+
+    let header_start = contents.find(UNRELEASED).unwrap();
+    let body_start = match contents[header_start..].find('\n') {
+        Some(end) => header_start + end + 1,
+        None => contents.len(),
+    };
+    let section_end = SECTION_END
+        .find(&contents[body_start..])
+        .map(|m| body_start + m.start())
+        .unwrap_or(contents.len());
+
+    let notes = contents[body_start..section_end].trim();
+    let release = if notes.is_empty() {
+        entry.trim_end().to_string()
+    } else {
+        let header = entry.lines().next().unwrap_or_default();
+        format!("{}\n\n{}", header, notes)
+    };
+
+    let new_contents = format!(
+        "{}\n\n{}\n\n{}",
+        contents[..body_start].trim_end(),
+        release,
+        &contents[section_end..]
+    );
+    Ok(format!("{}\n", new_contents.trim_end()))
+}
+
+/// Adds an empty Unreleased section above the first release when there is none.
+fn with_unreleased_section(contents: &str) -> String {
+    if contents.contains(UNRELEASED) {
+        return contents.to_owned();
+    }
+
+    let first_release = ENTRY_HEADING.find(contents);
+    match first_release {
+        Some(release) => format!(
+            "{}{}\n\n{}",
+            &contents[..release.start()],
+            UNRELEASED,
+            &contents[release.start()..]
+        ),
+        None => format!("{}\n\n{}\n", contents.trim_end(), UNRELEASED),
+    }
+}
+
+/// What comes before the first release, the header and the Unreleased section,
+/// without link definitions.
+pub fn preamble(contents: &str) -> String {
+    let first_release = ENTRY_HEADING
+        .find_iter(contents)
+        .find(|heading| !contents[heading.start()..].starts_with(UNRELEASED))
+        .map(|heading| heading.start())
+        .unwrap_or(contents.len());
+
+    let preamble = LINK_DEFINITION.replace_all(&contents[..first_release], "");
+    format!("{}\n", preamble.trim_end())
+}
+
+/// Points the `[Unreleased]` link definition past the new release and adds
+/// the definition of the released version below it.
+pub fn update_link_definitions(
+    contents: &str,
+    version: &str,
+    release_url: &str,
+    unreleased_url: &str,
+) -> String {
+    let unreleased = format!("[Unreleased]: {}", unreleased_url);
+    let released = format!("[{}]: {}", version, release_url);
+
+    let has_definition = |label: &str| {
+        LINK_DEFINITION
+            .captures_iter(contents)
+            .any(|caps| caps[1].eq_ignore_ascii_case(label))
+    };
+    let new_definitions = if has_definition(version) {
+        unreleased
+    } else {
+        format!("{}\n{}", unreleased, released)
+    };
+
+    let existing = LINK_DEFINITION
+        .find_iter(contents)
+        .find(|m| m.as_str().starts_with("[Unreleased]: "));
+    match (existing, LINK_DEFINITION.find(contents)) {
+        (Some(existing), _) => format!(
+            "{}{}{}",
+            &contents[..existing.start()],
+            new_definitions,
+            &contents[existing.end()..]
+        ),
+        (None, Some(first)) => format!(
+            "{}{}\n{}",
+            &contents[..first.start()],
+            new_definitions,
+            &contents[first.start()..]
+        ),
+        (None, None) => format!("{}\n\n{}\n", contents.trim_end(), new_definitions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static ENTRY: &str = "## [1.3.0] - 2026-10-17\n\n### Added\n\n- dark mode\n";
+
+    #[test]
+    fn test_promote_unreleased() {
+        let contents = format!(
+            "{}\n## [1.2.0] - 2026-09-01\n\n### Fixed\n\n- crash\n",
+            HEADER
+        );
+        let res = promote_unreleased(&contents, "1.3.0", ENTRY).unwrap();
+
+        assert_eq!(
+            res,
+            format!(
+                "{}\n{}\n## [1.2.0] - 2026-09-01\n\n### Fixed\n\n- crash\n",
+                HEADER, ENTRY
+            )
+        );
+        assert!(promote_unreleased(&res, "1.3.0", ENTRY).is_err());
+    }
+
+    #[test]
+    fn test_promote_unreleased_notes() {
+        let contents = "# Changelog\n\n## [Unreleased]\n\n### Removed\n\n- the v1 API\n\n\
+                        [Unreleased]: https://github.com/o/r/compare/v1.2.0...HEAD\n";
+        let res = promote_unreleased(contents, "1.3.0", ENTRY).unwrap();
+
+        assert_eq!(
+            res,
+            "# Changelog\n\n## [Unreleased]\n\n## [1.3.0] - 2026-10-17\n\n### Removed\n\n\
+             - the v1 API\n\n[Unreleased]: https://github.com/o/r/compare/v1.2.0...HEAD\n"
+        );
+    }
+
+    #[test]
+    fn test_promote_without_unreleased() {
+        let res = promote_unreleased("# Changelog\n", "1.3.0", ENTRY).unwrap();
+        assert_eq!(res, format!("# Changelog\n\n## [Unreleased]\n\n{}", ENTRY));
+
+        let contents = "# Changelog\n\n## [1.2.0] - 2026-09-01\n";
+        let res = promote_unreleased(contents, "1.3.0", ENTRY).unwrap();
+        assert_eq!(
+            res,
+            format!(
+                "# Changelog\n\n## [Unreleased]\n\n{}\n## [1.2.0] - 2026-09-01\n",
+                ENTRY
+            )
+        );
+    }
+
+    #[test]
+    fn test_preamble() {
+        let contents =
+            "# Changelog\n\nIntro\n\n## [Unreleased]\n\n- wip\n\n## [1.0.0] - 2026-09-01\n\n\
+                        [Unreleased]: https://github.com/o/r/compare/v1.0.0...HEAD\n";
+        assert_eq!(
+            preamble(contents),
+            "# Changelog\n\nIntro\n\n## [Unreleased]\n\n- wip\n"
+        );
+
+        let contents = "# Changelog\n\n## [Unreleased]\n\n\
+                        [Unreleased]: https://github.com/o/r/commits/HEAD\n";
+        assert_eq!(preamble(contents), "# Changelog\n\n## [Unreleased]\n");
+    }
+
+    #[test]
+    fn test_update_link_definitions() {
+        let contents = "## [1.2.0] - 2026-09-01\n\n\
+                        [Unreleased]: https://github.com/o/r/compare/v1.2.0...HEAD\n\
+                        [1.2.0]: https://github.com/o/r/releases/tag/v1.2.0\n";
+        let res = update_link_definitions(
+            contents,
+            "1.3.0",
+            "https://github.com/o/r/compare/v1.2.0...v1.3.0",
+            "https://github.com/o/r/compare/v1.3.0...HEAD",
+        );
+
+        assert_eq!(
+            res,
+            "## [1.2.0] - 2026-09-01\n\n\
+             [Unreleased]: https://github.com/o/r/compare/v1.3.0...HEAD\n\
+             [1.3.0]: https://github.com/o/r/compare/v1.2.0...v1.3.0\n\
+             [1.2.0]: https://github.com/o/r/releases/tag/v1.2.0\n"
+        );
+    }
+
+    #[test]
+    fn test_add_link_definitions() {
+        let res = update_link_definitions(
+            "## [1.0.0] - 2026-09-01\n\n- first\n",
+            "1.0.0",
+            "https://github.com/o/r/releases/tag/v1.0.0",
+            "https://github.com/o/r/compare/v1.0.0...HEAD",
+        );
+
+        assert_eq!(
+            res,
+            "## [1.0.0] - 2026-09-01\n\n- first\n\n\
+             [Unreleased]: https://github.com/o/r/compare/v1.0.0...HEAD\n\
+             [1.0.0]: https://github.com/o/r/releases/tag/v1.0.0\n"
+        );
+    }
+}
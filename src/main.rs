@@ -6,22 +6,35 @@ extern crate log;
 extern crate semver;
 extern crate toml_edit;
 
+mod changelog_date;
 mod changelog_gen;
+mod changelog_template;
 mod cli;
 mod commit;
+mod commit_filter;
+mod commit_template;
+mod config;
 mod git;
 mod github;
+mod hooks;
+mod json_edit;
+mod keep_a_changelog;
+mod release;
+mod tag;
 mod update_version;
 mod version_file;
+mod workspace;
+mod xml_edit;
 
-use crate::changelog_gen::ChangelogGenerator;
+use crate::config::Config;
 use crate::git::in_git_repository;
-use crate::github::GithubClient;
-use crate::update_version::{map_version_type, update_version, VersionType};
+use crate::github::{GithubClient, RepositoryLinks};
+use crate::release::{changed_packages, ReleaseTarget, Releaser};
+use crate::tag::TagTemplate;
+use crate::update_version::map_version_type;
 use crate::version_file::VersionFile;
 use clap::Parser;
-use cli::CliArgs;
-use dialoguer::Confirm;
+use cli::{CliArgs, Command};
 use env_logger::Env;
 use eyre::Result;
 use std::io::Write;
@@ -30,13 +43,7 @@ use std::io::Write;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let CliArgs {
-        version_type,
-        version_file,
-        repo,
-        main_branch,
-        personal_token,
-    } = cli::CliArgs::parse();
+    let args = cli::CliArgs::parse();
 
     let log_env = Env::default().default_filter_or("info");
     env_logger::Builder::from_env(log_env)
@@ -45,71 +52,140 @@ async fn main() -> Result<()> {
 
     in_git_repository()?;
 
-    let gh_client = GithubClient::new(&repo, &personal_token)?;
-
-    let version_type = map_version_type(&version_type)?;
-    let change_gen = ChangelogGenerator::new();
-
-    let mut version_file = VersionFile::new(&version_file)?;
-
-    // 1. Get current version value
-    let current_ver = version_file.get_version_value();
-    info!("📝 Current version is v{}", current_ver);
-
-    // 2. Get the new version value
-    let new_ver = &update_version(current_ver.to_owned(), version_type)?;
-    debug!("📝 New version is v{}", new_ver);
-
-    let prompt_text = format!("Do you want to release v{}?", new_ver);
-    if !Confirm::new().with_prompt(prompt_text).interact()? {
-        std::process::exit(0);
+    let mut config = Config::load(&args.config)?;
+    config.changelog.date = args.date.to_owned();
+    let version_type = map_version_type(&args.version_type)?;
+
+    match &args.command {
+        Some(Command::Release { package }) => {
+            releaser(&args, &config)?
+                .release_packages(&config, &[package], version_type)
+                .await
+        }
+        Some(Command::Changed { release }) => {
+            let changed = changed_packages(&config, &args.main_branch)?;
+            if changed.is_empty() {
+                info!("📭 No packages changed since their last release");
+                return Ok(());
+            }
+
+            if *release {
+                releaser(&args, &config)?
+                    .release_packages(&config, &changed, version_type)
+                    .await
+            } else {
+                for name in changed {
+                    println!("{}", name);
+                }
+                Ok(())
+            }
+        }
+        Some(Command::Changelog {
+            package,
+            rebuild,
+            from,
+            to,
+            output,
+            explain,
+        }) => {
+            let mut target = release_target(&args, &config, package.as_deref())?;
+            if let Some(repo) = &args.repo {
+                target
+                    .changelog
+                    .set_links(RepositoryLinks::for_project(repo));
+            }
+
+            if !rebuild {
+                let entry = target.preview_changelog(
+                    &args.main_branch,
+                    from.as_deref(),
+                    to.as_deref(),
+                    version_type,
+                )?;
+                let preview = match output.as_str() {
+                    "plain" => target.changelog.compact_changelog(&entry)?,
+                    "json" => target.changelog.json_changelog(&entry)?,
+                    _ => target.changelog.markdown_changelog(&entry)?,
+                };
+                println!("{}", preview.trim_end());
+                if *explain {
+                    info!("🚮 {} commits left out", entry.dropped.len());
+                    for dropped in &entry.dropped {
+                        info!(
+                            "  {} {}: {}",
+                            dropped.commit.abbreviated_commit,
+                            dropped.commit.subject,
+                            dropped.reason
+                        );
+                    }
+                }
+                return Ok(());
+            }
+
+            let releases = target.releases(&args.main_branch)?;
+            target.changelog.rebuild_changelog(&releases)?;
+            info!(
+                "📚 Rebuilt {} from {} releases",
+                target.changelog.changelog_path(),
+                releases.len()
+            );
+            Ok(())
+        }
+        None => {
+            let mut target = release_target(&args, &config, None)?;
+            releaser(&args, &config)?
+                .release(&mut target, version_type)
+                .await
+        }
     }
+}
 
-    version_file.update_version_file(new_ver)?;
-
-    // 3. Commit version file change and push that plus the new tag
-    git::add_files(version_file.get_tracked_files())?;
-    git::commit(&format!("chore: releasing {}", new_ver))?;
-
-    // 4. Generate a changelog, stage the CHANGELOG.md, commit that and push
-    let changelog = change_gen.generate_changelog(&main_branch, new_ver).await?;
-    let new_git_tag = &format!("v{}", new_ver.to_string());
-    git::tag(new_git_tag)?; // tagged commit, new version is name and version
-    git::add_files(vec!["CHANGELOG.md".to_owned()])?;
-    git::commit("docs: updating changelog [ci skip]")?;
-
-    // 5. Bump the working release number to prerelease
-    let current_ver = version_file.get_version_value();
-    let pre_ver = &update_version(current_ver.to_owned(), VersionType::Prerelease)?;
-    version_file.update_version_file(pre_ver)?;
-
-    // 6. Commit and push updated package.json file
-    git::add_files(version_file.get_tracked_files())?;
-    git::commit(&format!(
-        "chore: beginning development on {} [ci skip]",
-        pre_ver
-    ))?;
-    info!("📡 Pushing updates");
-    git::push(&main_branch)?;
-    git::push_tag(new_git_tag)?;
-
-    info!("🧾 Creating a GitHub release");
-    let tag_commit = git::get_commit_for_tag(new_git_tag)?;
-    gh_client
-        .create_new_release(
-            new_git_tag,
-            &tag_commit,
-            &change_gen.markdown_changelog(&changelog, None),
-        )
-        .await?;
-
-    info!(
-        "📖 Here are the changes for v{}:\n{}",
-        new_ver,
-        change_gen.compact_changelog(&changelog)
-    );
+/// The named package of a monorepo, or the whole repository with the version file
+/// from the arguments.
+fn release_target(args: &CliArgs, config: &Config, package: Option<&str>) -> Result<ReleaseTarget> {
+    if let Some(package) = package {
+        return ReleaseTarget::for_package(config.package(package)?, config);
+    }
 
-    info!("🚀 v{} has shipped!", new_ver);
+    let version_file_config = args
+        .version_file_config()
+        .ok_or_else(|| eyre!("A version file is required, see --file"))?;
+    let tag_template = match args.tag_template.as_ref().or(config.tag_template.as_ref()) {
+        Some(template) => TagTemplate::new(template)?,
+        None => TagTemplate::default(),
+    };
+    ReleaseTarget::new(
+        VersionFile::from_config(&version_file_config)?,
+        tag_template,
+        config,
+    )
+}
 
-    Ok(())
+/// Creates a releaser from the GitHub arguments, which are needed to publish a release.
+fn releaser(args: &CliArgs, config: &Config) -> Result<Releaser> {
+    let repo = args
+        .repo
+        .as_ref()
+        .ok_or_else(|| eyre!("The GitHub project is required, see --repo"))?;
+    let mut releaser = Releaser::new(RepositoryLinks::for_project(repo), &args.main_branch)
+        .with_tag_options(args.tag_options())
+        .with_commit_options(args.commit_options())
+        .with_push_options(args.push_options())
+        .with_hooks(config.hooks.to_owned());
+    // Nothing is pushed or published with --no-push, so it doesn't need a token
+    if !args.no_push {
+        let personal_token = args
+            .personal_token
+            .as_ref()
+            .ok_or_else(|| eyre!("A GitHub token is required, see --token"))?;
+        releaser = releaser.with_github(GithubClient::new(repo, personal_token)?);
+    }
+    if args.pull_request {
+        releaser = releaser.with_pull_request();
+    }
+    if args.maintenance {
+        releaser.maintenance()
+    } else {
+        Ok(releaser)
+    }
 }
@@ -0,0 +1,1005 @@
+use crate::changelog_date::ChangelogDates;
+use crate::changelog_gen::{ChangelogEntry, ChangelogGenerator, ReleaseInfo};
+use crate::changelog_template::ChangelogTemplates;
+use crate::commit_filter::CommitFilter;
+use crate::commit_template::{CommitTemplate, ReleaseCommits};
+use crate::config::{Config, HooksConfig, PackageConfig};
+use crate::git::{self, CommitOptions, PushOptions, TagOptions};
+use crate::github::{GithubClient, MergedPullRequest, RepositoryLinks};
+use crate::hooks::{run_hooks, HookEnv};
+use crate::tag::TagTemplate;
+use crate::update_version::{
+    map_version_type, next_development_version, next_prerelease, update_version, VersionType,
+};
+use crate::version_file::VersionFile;
+use crate::workspace::Workspace;
+use dialoguer::Confirm;
+use eyre::Result;
+use semver::Version;
+
+/// What gets released, either the whole repository or a single package of a monorepo.
+pub struct ReleaseTarget {
+    /// Name of the package, `None` for the whole repository
+    pub package: Option<String>,
+    pub version_file: VersionFile,
+    pub tag_template: TagTemplate,
+    pub changelog: ChangelogGenerator,
+    /// Files changed for the release besides the version file
+    pub extra_files: Vec<String>,
+    pub commits: ReleaseCommits,
+    /// Identifier of prereleases and development versions, e.g. `SNAPSHOT`
+    pub prerelease_identifier: String,
+    /// How to bump to the next development version, `None` to stay on the released version
+    pub development: Option<VersionType>,
+}
+
+impl ReleaseTarget {
+    pub fn new(
+        version_file: VersionFile,
+        tag_template: TagTemplate,
+        config: &Config,
+    ) -> Result<Self> {
+        ReleaseTarget {
+            package: None,
+            version_file,
+            tag_template,
+            changelog: ChangelogGenerator::new(),
+            extra_files: vec![],
+            commits: ReleaseCommits::from_config(&config.commits, false),
+            prerelease_identifier: String::new(),
+            development: None,
+        }
+        .configure(config)
+    }
+
+    pub fn for_package(package: &PackageConfig, config: &Config) -> Result<Self> {
+        ReleaseTarget {
+            package: Some(package.name.to_owned()),
+            version_file: VersionFile::from_config(&package.version_file_config())?,
+            tag_template: TagTemplate::new(&package.tag_template)?,
+            changelog: ChangelogGenerator::for_package(&package.path),
+            extra_files: vec![],
+            commits: ReleaseCommits::from_config(&config.commits, true),
+            prerelease_identifier: String::new(),
+            development: None,
+        }
+        .configure(config)
+    }
+
+    /// Applies the release settings shared by all targets.
+    fn configure(mut self, config: &Config) -> Result<Self> {
+        self.changelog.set_filter(CommitFilter::new(
+            self.commits.subject_patterns()?,
+            &config.changelog,
+        )?);
+        self.changelog.set_format(config.changelog.format);
+        self.changelog
+            .set_dates(ChangelogDates::from_config(&config.changelog)?);
+        self.changelog
+            .set_templates(ChangelogTemplates::load(&config.changelog)?);
+
+        self.prerelease_identifier = match &config.development.identifier {
+            Some(identifier) => identifier.to_owned(),
+            None => self
+                .version_file
+                .version_filetype
+                .prerelease_identifier()
+                .to_string(),
+        };
+        if config.development.enabled {
+            self.development = Some(map_version_type(&config.development.bump)?);
+        }
+
+        Ok(self)
+    }
+
+    fn package_name(&self) -> &str {
+        self.package.as_deref().unwrap_or_default()
+    }
+
+    /// The version the current version is bumped to, prereleases use the
+    /// prerelease identifier, e.g. `1.4.1-SNAPSHOT`.
+    pub fn bumped_version(&self, version_type: VersionType) -> Result<Version> {
+        let current_ver = self.version_file.get_version_value().to_owned();
+        match version_type {
+            VersionType::Prerelease => next_prerelease(current_ver, &self.prerelease_identifier),
+            _ => update_version(current_ver, version_type),
+        }
+    }
+
+    /// The git tag of the given version, e.g. `v1.4.0` or `pkg-a@1.4.0`.
+    pub fn tag_name(&self, version: &Version) -> String {
+        self.tag_template.format(self.package_name(), version)
+    }
+
+    /// The tag of the highest version released from the branch.
+    pub fn previous_tag(&self, branch: &str) -> Result<Option<String>> {
+        let tags = git::tags_merged_into(branch)?;
+        let previous_tag = self.tag_template.latest(self.package_name(), &tags);
+        Ok(previous_tag.map(String::from))
+    }
+
+    /// Every release of this target on the branch, lowest version first.
+    pub fn releases(&self, branch: &str) -> Result<Vec<ReleaseInfo>> {
+        let tags = git::tags_merged_into(branch)?;
+        let mut releases: Vec<ReleaseInfo> = vec![];
+        for (version, tag) in self.tag_template.sorted(self.package_name(), &tags) {
+            let previous = releases.last();
+            let release = ReleaseInfo {
+                version: version.to_string(),
+                tag: tag.to_owned(),
+                previous_version: previous.map(|release| release.version.to_owned()),
+                previous_tag: previous.map(|release| release.tag.to_owned()),
+            };
+            releases.push(release);
+        }
+        Ok(releases)
+    }
+
+    /// The changelog entry between two refs without changing anything. A release tag as `to`
+    /// previews that release, any other ref the next version after the current one. `from`
+    /// defaults to the release before.
+    pub fn preview_changelog(
+        &self,
+        branch: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+        version_type: VersionType,
+    ) -> Result<ChangelogEntry> {
+        let to = to.unwrap_or(branch);
+        let released = self.tag_template.parse(self.package_name(), to);
+
+        let (version, tag) = match &released {
+            Some(version) => (version.to_owned(), to.to_owned()),
+            None => {
+                let version = self.bumped_version(version_type)?;
+                let tag = self.tag_name(&version);
+                (version, tag)
+            }
+        };
+        let previous_tag = match (from, &released) {
+            (Some(from), _) => Some(from.to_owned()),
+            (None, None) => self.previous_tag(to)?,
+            (None, Some(_)) => {
+                let tags = git::tags_merged_into(to)?;
+                self.tag_template
+                    .sorted(self.package_name(), &tags)
+                    .into_iter()
+                    .rev()
+                    .find(|(previous_version, _)| previous_version < &version)
+                    .map(|(_, previous_tag)| previous_tag.to_owned())
+            }
+        };
+        let previous_version = previous_tag
+            .as_ref()
+            .and_then(|tag| self.tag_template.parse(self.package_name(), tag))
+            .map(|version| version.to_string());
+
+        let release = ReleaseInfo {
+            version: version.to_string(),
+            tag,
+            previous_version,
+            previous_tag,
+        };
+        self.changelog.changelog_entry(to, release)
+    }
+
+    /// The new version and the previous release from the branch.
+    pub fn release_info(&self, branch: &str, version: &Version) -> Result<ReleaseInfo> {
+        let previous_tag = self.previous_tag(branch)?;
+        let previous_version = previous_tag
+            .as_ref()
+            .and_then(|tag| self.tag_template.parse(self.package_name(), tag))
+            .map(|version| version.to_string());
+
+        Ok(ReleaseInfo {
+            version: version.to_string(),
+            tag: self.tag_name(version),
+            previous_version,
+            previous_tag,
+        })
+    }
+
+    /// Whether there are commits for this target since its previous release.
+    pub fn has_changes(&self, main_branch: &str) -> Result<bool> {
+        let previous_tag = self.previous_tag(main_branch)?;
+        let commits = self
+            .changelog
+            .commits_since(main_branch, previous_tag.as_deref())?;
+        Ok(!commits.is_empty())
+    }
+
+    /// Describes a version in logs, e.g. `v1.4.0` or `pkg-a v1.4.0`.
+    pub fn display(&self, version: &Version) -> String {
+        match &self.package {
+            Some(name) => format!("{} v{}", name, version),
+            None => format!("v{}", version),
+        }
+    }
+
+    /// Creates a commit message for the given version.
+    fn commit_message(&self, template: &CommitTemplate, version: &Version) -> String {
+        template.format(self.package_name(), version, &self.tag_name(version))
+    }
+}
+
+pub struct Releaser {
+    /// `None` when the release is neither pushed nor published
+    gh_client: Option<GithubClient>,
+    links: RepositoryLinks,
+    main_branch: String,
+    /// Major version line of a maintenance branch like `release/1.x`
+    maintenance_major: Option<u64>,
+    tag_options: TagOptions,
+    commit_options: CommitOptions,
+    /// Open a release pull request instead of pushing to the main branch
+    pull_request: bool,
+    /// `None` leaves pushing the release to the user
+    push_options: Option<PushOptions>,
+    hooks: HooksConfig,
+}
+
+impl Releaser {
+    pub fn new(links: RepositoryLinks, main_branch: &str) -> Self {
+        Releaser {
+            gh_client: None,
+            links,
+            main_branch: main_branch.to_owned(),
+            maintenance_major: None,
+            tag_options: TagOptions::default(),
+            commit_options: CommitOptions::default(),
+            pull_request: false,
+            push_options: Some(PushOptions::default()),
+            hooks: HooksConfig::default(),
+        }
+    }
+
+    pub fn with_github(mut self, gh_client: GithubClient) -> Self {
+        self.gh_client = Some(gh_client);
+        self
+    }
+
+    /// The GitHub client, everything that publishes the release needs it.
+    fn github(&self) -> Result<&GithubClient> {
+        self.gh_client
+            .as_ref()
+            .ok_or_else(|| eyre!("A GitHub token is required, see --token"))
+    }
+
+    pub fn with_hooks(mut self, hooks: HooksConfig) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn with_push_options(mut self, push_options: Option<PushOptions>) -> Self {
+        self.push_options = push_options;
+        self
+    }
+
+    /// Where the pull request workflow pushes to, it can't work without pushing.
+    fn pull_request_push_options(&self) -> Result<&PushOptions> {
+        self.push_options
+            .as_ref()
+            .ok_or_else(|| eyre!("Release pull requests need to be pushed, see --no-push"))
+    }
+
+    pub fn with_tag_options(mut self, tag_options: TagOptions) -> Self {
+        self.tag_options = tag_options;
+        self
+    }
+
+    pub fn with_commit_options(mut self, commit_options: CommitOptions) -> Self {
+        self.commit_options = commit_options;
+        self
+    }
+
+    /// Proposes releases in a pull request, the merged pull request is
+    /// tagged and published by the next run.
+    pub fn with_pull_request(mut self) -> Self {
+        self.pull_request = true;
+        self
+    }
+
+    /// Releases from a maintenance branch only bump versions within its major version
+    /// and are not marked as the latest release on GitHub.
+    pub fn maintenance(mut self) -> Result<Self> {
+        let major = maintenance_major(&self.main_branch).ok_or_else(|| {
+            eyre!(
+                "Maintenance branch {} must end with its major version, e.g. release/1.x",
+                self.main_branch
+            )
+        })?;
+        self.maintenance_major = Some(major);
+        Ok(self)
+    }
+
+    /// Bumps the version, updates the changelog, tags and pushes the release
+    /// and creates a GitHub release for it.
+    pub async fn release(
+        &self,
+        target: &mut ReleaseTarget,
+        version_type: VersionType,
+    ) -> Result<()> {
+        ensure_clean_working_tree()?;
+        if self.pull_request {
+            // The version file of a merged release pull request holds the untagged release
+            let current_ver = target.version_file.get_version_value().to_owned();
+            let current_tag = target.tag_name(&current_ver);
+            if !git::tag_exists(&current_tag)? {
+                let branch = release_branch(&current_tag);
+                if let Some(pull) = self.github()?.merged_pull_request(&branch).await? {
+                    return self.publish_pull_request(target, &current_ver, pull).await;
+                }
+            }
+        }
+
+        let new_ver = &self.next_version(target, version_type)?;
+
+        let prompt_text = format!("Do you want to release {}?", target.display(new_ver));
+        if !Confirm::new().with_prompt(prompt_text).interact()? {
+            return Ok(());
+        }
+
+        if self.pull_request {
+            self.open_pull_request(target, new_ver).await
+        } else {
+            self.ship(target, new_ver).await
+        }
+    }
+
+    /// Releases packages of a monorepo, followed by a patch release of every
+    /// package depending on them with the dependency requirements updated.
+    pub async fn release_packages(
+        &self,
+        config: &Config,
+        names: &[&str],
+        version_type: VersionType,
+    ) -> Result<()> {
+        if self.pull_request {
+            return Err(eyre!(
+                "Release pull requests are not supported for monorepo packages"
+            ));
+        }
+        ensure_clean_working_tree()?;
+
+        let workspace = Workspace::load(config)?;
+        let mut releases = vec![];
+        for package in workspace.release_order(names, config.bump_dependents)? {
+            let target = ReleaseTarget::for_package(config.package(&package.name)?, config)?;
+            let new_ver = if names.contains(&package.name.as_str()) {
+                self.next_version(&target, version_type)?
+            } else {
+                self.next_version(&target, VersionType::Patch)?
+            };
+            releases.push((target, new_ver));
+        }
+
+        let release_names = releases
+            .iter()
+            .map(|(target, new_ver)| target.display(new_ver))
+            .collect::<Vec<_>>();
+        let prompt_text = format!("Do you want to release {}?", release_names.join(", "));
+        if !Confirm::new().with_prompt(prompt_text).interact()? {
+            return Ok(());
+        }
+
+        // Manifest name and new version of the packages released so far
+        let mut released: Vec<(String, Version)> = vec![];
+        for (mut target, new_ver) in releases {
+            let package = workspace.package(target.package_name());
+            let manifest = match package.and_then(|package| package.manifest.as_ref()) {
+                Some(manifest) => manifest,
+                None => {
+                    self.ship(&mut target, &new_ver).await?;
+                    continue;
+                }
+            };
+
+            let mut updated_dependencies = vec![];
+            for (dependency, dependency_ver) in &released {
+                if manifest.depends_on(dependency) {
+                    manifest.update_dependency(dependency, dependency_ver)?;
+                    updated_dependencies.push(format!("{}@{}", dependency, dependency_ver));
+                }
+            }
+            if !updated_dependencies.is_empty() {
+                target
+                    .changelog
+                    .set_updated_dependencies(updated_dependencies);
+                target.extra_files.push(manifest.path.to_owned());
+            }
+
+            self.ship(&mut target, &new_ver).await?;
+            released.push((manifest.name.to_owned(), new_ver));
+        }
+
+        Ok(())
+    }
+
+    async fn ship(&self, target: &mut ReleaseTarget, new_ver: &Version) -> Result<()> {
+        let new_git_tag = &target.tag_name(new_ver);
+        let env = self.hook_env(target, new_ver)?;
+        let start = git::head()?;
+
+        // Nothing has left the machine until the push, so a failure is rolled back
+        let changelog = match self.prepare_release(target, new_ver, &env).await {
+            Ok(changelog) => changelog,
+            Err(err) => {
+                self.rollback(&start, Some(new_git_tag), &self.release_files(target));
+                return Err(err);
+            }
+        };
+
+        let push_options = match &self.push_options {
+            Some(push_options) => push_options,
+            None => {
+                info!(
+                    "📦 {} is ready, push {} and {} to publish it",
+                    target.display(new_ver),
+                    self.main_branch,
+                    new_git_tag
+                );
+                return Ok(());
+            }
+        };
+        info!("📡 Pushing updates");
+        git::push(&[&self.main_branch, new_git_tag], push_options)?;
+
+        let tag_commit = git::get_commit_for_tag(new_git_tag)?;
+        let body = target.changelog.release_body(&changelog)?;
+        self.publish(target, new_ver, &tag_commit, &body).await?;
+        run_hooks("post_release", &self.hooks.post_release, &env)?;
+
+        info!(
+            "📖 Here are the changes for {}:\n{}",
+            target.display(new_ver),
+            target.changelog.compact_changelog(&changelog)?
+        );
+
+        info!("🚀 {} has shipped!", target.display(new_ver));
+
+        Ok(())
+    }
+
+    /// Makes the release commits and tag and the development version commit.
+    async fn prepare_release(
+        &self,
+        target: &mut ReleaseTarget,
+        new_ver: &Version,
+        env: &HookEnv,
+    ) -> Result<ChangelogEntry> {
+        let new_git_tag = &target.tag_name(new_ver);
+        let changelog = self.commit_release(target, new_ver, env, true).await?;
+        run_hooks("post_tag", &self.hooks.post_tag, env)?;
+
+        // 5. Bump the working release number to the next development version
+        if let Some(bump) = target.development {
+            let current_ver = target.version_file.get_version_value().to_owned();
+            let dev_ver =
+                &next_development_version(current_ver, bump, &target.prerelease_identifier)?;
+            target.version_file.update_version_file(dev_ver)?;
+
+            // 6. Commit and push updated version file
+            git::add_files(target.version_file.get_tracked_files())?;
+            git::commit(
+                &target.commit_message(&target.commits.development, dev_ver),
+                &self.commit_options,
+            )?;
+        }
+        if self.tag_options.sign {
+            info!("🔏 Verifying the signature of {}", new_git_tag);
+            git::verify_tag(new_git_tag, &self.tag_options)?;
+        }
+
+        Ok(changelog)
+    }
+
+    /// Undoes the commits, the tag and the file changes of a failed release,
+    /// leaving other changes in the working tree alone.
+    fn rollback(&self, start: &str, tag: Option<&str>, files: &[String]) {
+        warn!("↩️ Rolling back the release");
+        if let Some(tag) = tag {
+            if git::tag_exists(tag).unwrap_or(false) {
+                if let Err(err) = git::delete_tag(tag) {
+                    warn!("Could not delete {}: {}", tag, err);
+                }
+            }
+        }
+        if let Err(err) = git::reset_keep(start) {
+            warn!("Could not reset to {}: {}", start, err);
+        }
+        if let Err(err) = git::restore_files(start, files) {
+            warn!("Could not restore {}: {}", files.join(", "), err);
+        }
+    }
+
+    /// The files changed by a release of the target.
+    fn release_files(&self, target: &ReleaseTarget) -> Vec<String> {
+        let mut files = target.version_file.get_tracked_files();
+        files.extend(target.extra_files.iter().cloned());
+        files.extend(self.hooks.stage.iter().cloned());
+        files.push(target.changelog.changelog_path().to_owned());
+        files
+    }
+
+    fn hook_env(&self, target: &ReleaseTarget, new_ver: &Version) -> Result<HookEnv> {
+        let release = target.release_info(&self.main_branch, new_ver)?;
+
+        Ok(HookEnv {
+            version: release.version,
+            tag: release.tag,
+            previous_version: release.previous_version,
+            changelog_path: target.changelog.changelog_path().to_owned(),
+        })
+    }
+
+    /// Commits the release to a new branch and opens a pull request for it.
+    async fn open_pull_request(&self, target: &mut ReleaseTarget, new_ver: &Version) -> Result<()> {
+        let new_git_tag = &target.tag_name(new_ver);
+        let branch = release_branch(new_git_tag);
+        let env = self.hook_env(target, new_ver)?;
+        let start = git::head()?;
+        git::checkout_new_branch(&branch)?;
+
+        let changelog = match self.commit_release(target, new_ver, &env, false).await {
+            Ok(changelog) => changelog,
+            Err(err) => {
+                self.rollback(&start, None, &self.release_files(target));
+                git::checkout(&self.main_branch)?;
+                git::delete_branch(&branch)?;
+                return Err(err);
+            }
+        };
+
+        info!("📡 Pushing {}", branch);
+        git::push(&[&branch], self.pull_request_push_options()?)?;
+        git::checkout(&self.main_branch)?;
+
+        let url = self
+            .github()?
+            .create_pull_request(
+                &branch,
+                &self.main_branch,
+                &target.commit_message(&target.commits.release, new_ver),
+                &target.changelog.release_body(&changelog)?,
+            )
+            .await?;
+
+        info!(
+            "🔀 Opened a pull request for {}: {}",
+            target.display(new_ver),
+            url
+        );
+        info!("Run git-releaser again once it is merged to tag and publish the release");
+
+        Ok(())
+    }
+
+    /// Tags the merge commit of a release pull request and publishes the release.
+    async fn publish_pull_request(
+        &self,
+        target: &mut ReleaseTarget,
+        version: &Version,
+        pull: MergedPullRequest,
+    ) -> Result<()> {
+        let new_git_tag = &target.tag_name(version);
+        let number = pull.number;
+        let merge_commit = pull
+            .merge_commit_sha
+            .ok_or_else(|| eyre!("Pull request #{} has no merge commit", number))?;
+        info!(
+            "🔀 Pull request #{} for {} has been merged",
+            number,
+            target.display(version)
+        );
+        debug!(
+            "Pull request #{} description:\n{}",
+            number,
+            pull.body.unwrap_or_default()
+        );
+
+        let env = self.hook_env(target, version)?;
+        let push_options = self.pull_request_push_options()?;
+        git::fetch(&self.main_branch, push_options)?;
+
+        // The description can be edited before the merge, the tag and the GitHub release
+        // get the changelog entry of the merged commit instead
+        let release = target.release_info(&merge_commit, version)?;
+        target.changelog.set_links(self.links.to_owned());
+        let mut changelog = target.changelog.changelog_entry(&merge_commit, release)?;
+        // A merge commit of the pull request isn't a change of its own
+        changelog
+            .commits
+            .retain(|commit| commit.commit != merge_commit);
+        let tag_message = target.changelog.markdown_changelog(&changelog)?;
+        git::tag_commit(new_git_tag, &merge_commit, &tag_message, &self.tag_options)?;
+
+        // Nothing has been pushed yet, so a failure only removes the tag again
+        let mut checked = Ok(());
+        if self.tag_options.sign {
+            info!("🔏 Verifying the signature of {}", new_git_tag);
+            checked = git::verify_tag(new_git_tag, &self.tag_options).map(|_| ());
+        }
+        let checked = checked.and_then(|_| run_hooks("post_tag", &self.hooks.post_tag, &env));
+        if let Err(err) = checked {
+            warn!("↩️ Removing {}", new_git_tag);
+            git::delete_tag(new_git_tag)?;
+            return Err(err);
+        }
+
+        info!("📡 Pushing {}", new_git_tag);
+        git::push(&[new_git_tag], push_options)?;
+        let body = target.changelog.release_body(&changelog)?;
+        self.publish(target, version, &merge_commit, &body).await?;
+        run_hooks("post_release", &self.hooks.post_release, &env)?;
+
+        info!(
+            "📖 Here are the changes for {}:\n{}",
+            target.display(version),
+            target.changelog.compact_changelog(&changelog)?
+        );
+
+        info!("🚀 {} has shipped!", target.display(version));
+
+        if let Some(bump) = target.development {
+            let dev_ver =
+                next_development_version(version.to_owned(), bump, &target.prerelease_identifier)?;
+            self.open_development_pull_request(target, version, &dev_ver, &merge_commit)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Proposes the bump to the next development version on top of the released commit.
+    async fn open_development_pull_request(
+        &self,
+        target: &mut ReleaseTarget,
+        version: &Version,
+        dev_ver: &Version,
+        start_point: &str,
+    ) -> Result<()> {
+        let branch = format!("{}-development", release_branch(&target.tag_name(version)));
+        let message = target.commit_message(&target.commits.development, dev_ver);
+        git::checkout_new_branch_from(&branch, start_point)?;
+
+        let committed = target
+            .version_file
+            .update_version_file(dev_ver)
+            .and_then(|_| git::add_files(target.version_file.get_tracked_files()))
+            .and_then(|_| git::commit(&message, &self.commit_options));
+        if let Err(err) = committed {
+            git::restore_files(start_point, &target.version_file.get_tracked_files())?;
+            git::checkout(&self.main_branch)?;
+            git::delete_branch(&branch)?;
+            return Err(err);
+        }
+
+        info!("📡 Pushing {}", branch);
+        git::push(&[&branch], self.pull_request_push_options()?)?;
+        git::checkout(&self.main_branch)?;
+
+        let body = format!(
+            "Continues development on {} after the release of {}.",
+            dev_ver,
+            target.display(version)
+        );
+        let url = self
+            .github()?
+            .create_pull_request(&branch, &self.main_branch, &message, &body)
+            .await?;
+        info!(
+            "🔀 Opened a pull request for the development version {}: {}",
+            dev_ver, url
+        );
+
+        Ok(())
+    }
+
+    async fn publish(
+        &self,
+        target: &ReleaseTarget,
+        version: &Version,
+        tag_commit: &str,
+        body: &str,
+    ) -> Result<()> {
+        info!(
+            "🧾 Creating a GitHub release for {}",
+            target.display(version)
+        );
+        self.github()?
+            .create_new_release(
+                target.tag_name(version).as_str(),
+                tag_commit,
+                body,
+                self.maintenance_major.is_none(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Commits the new version and the changelog, tagging the release commit
+    /// when `tag` is set. Returns the new changelog entry.
+    async fn commit_release(
+        &self,
+        target: &mut ReleaseTarget,
+        new_ver: &Version,
+        env: &HookEnv,
+        tag: bool,
+    ) -> Result<ChangelogEntry> {
+        target.version_file.update_version_file(new_ver)?;
+
+        // 3. Generate a changelog and let the hooks prepare the release
+        let release = target.release_info(&self.main_branch, new_ver)?;
+        let new_git_tag = &release.tag.to_owned();
+        target.changelog.set_links(self.links.to_owned());
+        let changelog = target
+            .changelog
+            .generate_changelog(&self.main_branch, release)
+            .await?;
+        let tag_message = target.changelog.markdown_changelog(&changelog)?;
+        run_hooks("pre_commit", &self.hooks.pre_commit, env)?;
+
+        // 4. Commit the version file change, tag it and commit the CHANGELOG.md
+        let mut tracked_files = target.version_file.get_tracked_files();
+        tracked_files.extend(target.extra_files.iter().cloned());
+        tracked_files.extend(self.hooks.stage.iter().cloned());
+        git::add_files(tracked_files)?;
+        let release_message = target.commit_message(&target.commits.release, new_ver);
+        if !target.commits.squash {
+            git::commit(&release_message, &self.commit_options)?;
+        }
+
+        git::add_files(vec![target.changelog.changelog_path().to_owned()])?;
+        if target.commits.squash {
+            git::commit(&release_message, &self.commit_options)?;
+            if tag {
+                git::tag(new_git_tag, &tag_message, &self.tag_options)?;
+            }
+        } else {
+            if tag {
+                git::tag(new_git_tag, &tag_message, &self.tag_options)?; // tagged commit, new version is name and version
+            }
+            git::commit(
+                &target.commit_message(&target.commits.changelog, new_ver),
+                &self.commit_options,
+            )?;
+        }
+
+        Ok(changelog)
+    }
+
+    /// Gets the current version and the version it is bumped to.
+    fn next_version(&self, target: &ReleaseTarget, version_type: VersionType) -> Result<Version> {
+        // 1. Get current version value
+        let current_ver = target.version_file.get_version_value();
+        info!("📝 Current version is {}", target.display(current_ver));
+
+        // 2. Get the new version value
+        let new_ver = target.bumped_version(version_type)?;
+        debug!("📝 New version is {}", target.display(&new_ver));
+
+        if let Some(major) = self.maintenance_major {
+            if !matches!(version_type, VersionType::Patch | VersionType::Minor) {
+                return Err(eyre!(
+                    "Only patch and minor releases can be made from {}",
+                    self.main_branch
+                ));
+            }
+            if new_ver.major != major {
+                return Err(eyre!(
+                    "{} is outside of the {}.x versions released from {}",
+                    target.display(&new_ver),
+                    major,
+                    self.main_branch
+                ));
+            }
+        }
+
+        let new_tag = target.tag_name(&new_ver);
+        if git::tag_exists(&new_tag)? {
+            return Err(eyre!("{} has already been released", new_tag));
+        }
+        // A maintenance checkout may not have fetched the tags released from main
+        if let Some(push_options) = &self.push_options {
+            for remote in &push_options.remotes {
+                if git::remote_tag_exists(&new_tag, remote)? {
+                    return Err(eyre!("{} has already been released on {}", new_tag, remote));
+                }
+            }
+        }
+
+        Ok(new_ver)
+    }
+}
+
+/// Names of the packages with commits touching their directory since their last tag.
+pub fn changed_packages<'a>(config: &'a Config, main_branch: &str) -> Result<Vec<&'a str>> {
+    let mut changed = vec![];
+    for package in &config.packages {
+        if ReleaseTarget::for_package(package, config)?.has_changes(main_branch)? {
+            changed.push(package.name.as_str());
+        } else {
+            debug!("No changes in {} since its last release", package.name);
+        }
+    }
+    Ok(changed)
+}
+
+/// Releases start from a clean working tree, so rolling back can't lose any changes.
+fn ensure_clean_working_tree() -> Result<()> {
+    let changes = git::uncommitted_changes()?;
+    if !changes.is_empty() {
+        return Err(eyre!(
+            "Commit or stash your changes before releasing:\n{}",
+            changes.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// The branch of the release pull request for a tag.
+fn release_branch(tag: &str) -> String {
+    format!("git-releaser/{}", tag)
+}
+
+/// Parses the major version of a maintenance branch, e.g. `1` for `release/1.x`.
+fn maintenance_major(branch: &str) -> Option<u64> {
+    let name = branch.rsplit('/').next()?;
+    name.strip_suffix(".x")?
+        .trim_start_matches('v')
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version_file::VersionFiletype;
+    use std::fs;
+
+    fn target(package: Option<&str>, tag_template: &str) -> ReleaseTarget {
+        ReleaseTarget {
+            package: package.map(String::from),
+            version_file: VersionFile {
+                filename: "package.json".to_string(),
+                version_value: Version::parse("1.3.0").unwrap(),
+                version_filetype: VersionFiletype::JSON,
+                lockfile: None,
+                key_path: VersionFiletype::JSON.default_key_path(),
+                bump_version_code: false,
+            },
+            tag_template: TagTemplate::new(tag_template).unwrap(),
+            changelog: ChangelogGenerator::new(),
+            extra_files: vec![],
+            commits: ReleaseCommits::from_config(&Default::default(), package.is_some()),
+            prerelease_identifier: "0".to_string(),
+            development: None,
+        }
+    }
+
+    /// Ships 1.4.0 of a package.json in a new repository with a failing hook,
+    /// which also changes a file unrelated to the release.
+    async fn ship_with_failing_hook(name: &str, hooks: fn(&str) -> HooksConfig) {
+        let dir = git::enter_test_repo(name);
+        let version_file = dir.join("package.json");
+        let notes = dir.join("notes.txt");
+        let original = "{\n  \"version\": \"1.3.0\"\n}\n";
+        fs::write(&version_file, original).unwrap();
+        fs::write(&notes, "draft\n").unwrap();
+        git::add_files(vec!["package.json".to_string(), "notes.txt".to_string()]).unwrap();
+        git::commit("feat: notes", &CommitOptions::default()).unwrap();
+        let start = git::head().unwrap();
+
+        let mut target = target(None, "v{version}");
+        target.version_file.filename = version_file.to_string_lossy().to_string();
+        target.changelog = ChangelogGenerator::for_package(&dir.to_string_lossy());
+        let releaser = Releaser::new(RepositoryLinks::for_project("o/r"), "HEAD")
+            .with_hooks(hooks(&notes.to_string_lossy()))
+            .with_push_options(None);
+
+        let res = releaser
+            .ship(&mut target, &Version::parse("1.4.0").unwrap())
+            .await;
+        let head = git::head().unwrap();
+        let tagged = git::tag_exists("v1.4.0").unwrap();
+        let version = fs::read_to_string(&version_file).unwrap();
+        let notes = fs::read_to_string(&notes).unwrap();
+        git::leave_test_repo();
+
+        assert!(res.is_err());
+        assert_eq!(head, start);
+        assert!(!tagged);
+        assert_eq!(version, original);
+        assert_eq!(notes, "generated\n");
+    }
+
+    #[tokio::test]
+    async fn test_failing_pre_commit_hook_keeps_local_changes() {
+        ship_with_failing_hook("pre-commit-rollback-test", |notes| HooksConfig {
+            pre_commit: vec![format!("echo generated > {} && exit 1", notes)],
+            ..HooksConfig::default()
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_failing_post_tag_hook_keeps_local_changes() {
+        ship_with_failing_hook("post-tag-rollback-test", |notes| HooksConfig {
+            post_tag: vec![format!("echo generated > {} && exit 1", notes)],
+            ..HooksConfig::default()
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_bumped_version_prerelease() {
+        let repo = target(None, "v{version}");
+        assert_eq!(
+            repo.bumped_version(VersionType::Prerelease)
+                .unwrap()
+                .to_string(),
+            "1.3.1-0"
+        );
+
+        let mut version_file = target(None, "v{version}").version_file;
+        version_file.version_filetype = VersionFiletype::Maven;
+        let tag_template = TagTemplate::new("v{version}").unwrap();
+        let maven = ReleaseTarget::new(version_file, tag_template, &Config::default()).unwrap();
+        assert_eq!(
+            maven
+                .bumped_version(VersionType::Prerelease)
+                .unwrap()
+                .to_string(),
+            "1.3.1-SNAPSHOT"
+        );
+
+        let config = Config::parse("[development]\nenabled = false\nidentifier = \"rc\"").unwrap();
+        let version_file = target(None, "v{version}").version_file;
+        let tag_template = TagTemplate::new("v{version}").unwrap();
+        let rc = ReleaseTarget::new(version_file, tag_template, &config).unwrap();
+        assert_eq!(
+            rc.bumped_version(VersionType::Prerelease)
+                .unwrap()
+                .to_string(),
+            "1.3.1-rc"
+        );
+        assert_eq!(rc.development, None);
+    }
+
+    #[test]
+    fn test_tag_name() {
+        let version = Version::parse("1.4.0").unwrap();
+
+        let repo = target(None, "v{version}");
+        assert_eq!(repo.tag_name(&version), "v1.4.0");
+        assert_eq!(
+            repo.commit_message(&repo.commits.release, &version),
+            "chore: releasing 1.4.0"
+        );
+        assert_eq!(repo.display(&version), "v1.4.0");
+
+        let package = target(Some("pkg-a"), "{name}@{version}");
+        assert_eq!(package.tag_name(&version), "pkg-a@1.4.0");
+        assert_eq!(
+            package.commit_message(&package.commits.release, &version),
+            "chore: releasing pkg-a@1.4.0"
+        );
+        assert_eq!(package.display(&version), "pkg-a v1.4.0");
+    }
+
+    #[test]
+    fn test_release_branch() {
+        assert_eq!(release_branch("v1.4.0"), "git-releaser/v1.4.0");
+        assert_eq!(release_branch("pkg-a@1.4.0"), "git-releaser/pkg-a@1.4.0");
+    }
+
+    #[test]
+    fn test_maintenance_major() {
+        assert_eq!(maintenance_major("release/1.x"), Some(1));
+        assert_eq!(maintenance_major("v12.x"), Some(12));
+        assert_eq!(maintenance_major("main"), None);
+        assert_eq!(maintenance_major("release/1.2"), None);
+    }
+}
@@ -0,0 +1,146 @@
+use eyre::Result;
+use semver::Version;
+
+static NAME_PLACEHOLDER: &str = "{name}";
+static VERSION_PLACEHOLDER: &str = "{version}";
+
+/// Template for git tag names, e.g. `v{version}` or `{name}@{version}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagTemplate {
+    template: String,
+}
+
+impl TagTemplate {
+    pub fn new(template: &str) -> Result<Self> {
+        if template.matches(VERSION_PLACEHOLDER).count() != 1 {
+            return Err(eyre!(
+                "Tag template '{}' must contain {} exactly once",
+                template,
+                VERSION_PLACEHOLDER
+            ));
+        }
+
+        Ok(TagTemplate {
+            template: template.to_owned(),
+        })
+    }
+
+    /// Creates the tag name for a version of the named package.
+    pub fn format(&self, name: &str, version: &Version) -> String {
+        self.template
+            .replace(NAME_PLACEHOLDER, name)
+            .replace(VERSION_PLACEHOLDER, &version.to_string())
+    }
+
+    /// Extracts the version from a tag created by this template.
+    /// Returns `None` for tags that do not match.
+    pub fn parse(&self, name: &str, tag: &str) -> Option<Version> {
+        let template = self.template.replace(NAME_PLACEHOLDER, name);
+        let (prefix, suffix) = template.split_once(VERSION_PLACEHOLDER)?;
+
+        let version = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        Version::parse(version).ok()
+    }
+
+    /// Picks the tag of the highest version among the tags created by this template.
+    pub fn latest<'a>(&self, name: &str, tags: &'a [String]) -> Option<&'a str> {
+        self.sorted(name, tags).pop().map(|(_, tag)| tag)
+    }
+
+    /// The tags created by this template with their versions, lowest version first.
+    pub fn sorted<'a>(&self, name: &str, tags: &'a [String]) -> Vec<(Version, &'a str)> {
+        let mut releases = tags
+            .iter()
+            .filter_map(|tag| self.parse(name, tag).map(|version| (version, tag.as_str())))
+            .collect::<Vec<_>>();
+        releases.sort_by(|(a, _), (b, _)| a.cmp(b));
+        releases
+    }
+}
+
+impl Default for TagTemplate {
+    fn default() -> Self {
+        TagTemplate {
+            template: format!("v{}", VERSION_PLACEHOLDER),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_tag_template() {
+        assert!(TagTemplate::new("{name}@{version}").is_ok());
+        assert!(TagTemplate::new("release").is_err());
+        assert!(TagTemplate::new("{version}-{version}").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let version = Version::parse("1.4.0").unwrap();
+
+        assert_eq!(TagTemplate::default().format("", &version), "v1.4.0");
+        assert_eq!(
+            TagTemplate::new("{name}@{version}")
+                .unwrap()
+                .format("pkg-a", &version),
+            "pkg-a@1.4.0"
+        );
+        assert_eq!(
+            TagTemplate::new("{name}-v{version}")
+                .unwrap()
+                .format("pkg-a", &version),
+            "pkg-a-v1.4.0"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let template = TagTemplate::new("{name}-v{version}").unwrap();
+
+        assert_eq!(
+            template.parse("pkg-a", "pkg-a-v1.4.0"),
+            Some(Version::parse("1.4.0").unwrap())
+        );
+        assert_eq!(template.parse("pkg-a", "pkg-b-v1.4.0"), None);
+        assert_eq!(template.parse("pkg-a", "pkg-a-vnext"), None);
+        assert_eq!(TagTemplate::default().parse("", "deploy-prod"), None);
+    }
+
+    #[test]
+    fn test_latest() {
+        let tags = [
+            "v1.10.0",
+            "deploy-prod",
+            "v1.9.2",
+            "pkg-a@2.0.0",
+            "v1.10.0-0",
+        ]
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect::<Vec<_>>();
+
+        assert_eq!(TagTemplate::default().latest("", &tags), Some("v1.10.0"));
+        assert_eq!(
+            TagTemplate::new("{name}@{version}")
+                .unwrap()
+                .latest("pkg-a", &tags),
+            Some("pkg-a@2.0.0")
+        );
+        assert_eq!(
+            TagTemplate::new("release-{version}")
+                .unwrap()
+                .latest("", &tags),
+            None
+        );
+
+        let sorted = TagTemplate::default()
+            .sorted("", &tags)
+            .into_iter()
+            .map(|(_, tag)| tag)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["v1.9.2", "v1.10.0-0", "v1.10.0"]);
+    }
+}
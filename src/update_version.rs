@@ -1,7 +1,7 @@
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use semver::{Prerelease, Version};
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum VersionType {
     Prerelease,
     Patch,
@@ -40,8 +40,7 @@ pub fn update_version(mut version: Version, version_type: VersionType) -> Result
     match version_type {
         VersionType::Prerelease => {
             debug!("Prerelease");
-            version.patch += 1;
-            version.pre = Prerelease::new("0").unwrap();
+            return next_prerelease(version, "0");
         }
         VersionType::Patch => {
             debug!("Patch");
@@ -52,21 +51,51 @@ pub fn update_version(mut version: Version, version_type: VersionType) -> Result
         }
         VersionType::Minor => {
             debug!("Minor");
-            version.minor += 1;
-            version.patch = 0;
+            // A development version like 1.5.0-dev is released as 1.5.0
+            if version.pre.is_empty() || version.patch != 0 {
+                version.minor += 1;
+                version.patch = 0;
+            }
             version.pre = Prerelease::EMPTY;
         }
         VersionType::Major => {
             debug!("Major");
-            version.major += 1;
-            version.minor = 0;
-            version.patch = 0;
+            if version.pre.is_empty() || version.minor != 0 || version.patch != 0 {
+                version.major += 1;
+                version.minor = 0;
+                version.patch = 0;
+            }
             version.pre = Prerelease::EMPTY;
         }
     }
     Ok(version)
 }
 
+/// Bumps the patch version and marks it as a prerelease with the given
+/// identifier, e.g. `0.1.3-0` or `0.1.3-SNAPSHOT`.
+pub fn next_prerelease(mut version: Version, identifier: &str) -> Result<Version> {
+    version.patch += 1;
+    version.pre = Prerelease::new(identifier).wrap_err("Invalid prerelease identifier")?;
+    Ok(version)
+}
+
+/// The version to continue development on after a release, e.g. `0.2.0-dev`
+/// when bumping the minor version with the `dev` identifier.
+pub fn next_development_version(
+    version: Version,
+    bump: VersionType,
+    identifier: &str,
+) -> Result<Version> {
+    match bump {
+        VersionType::Prerelease | VersionType::Patch => next_prerelease(version, identifier),
+        VersionType::Minor | VersionType::Major => {
+            let mut version = update_version(version, bump)?;
+            version.pre = Prerelease::new(identifier).wrap_err("Invalid prerelease identifier")?;
+            Ok(version)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +123,36 @@ mod tests {
         assert_eq!(res.to_string(), "0.1.3-0");
     }
 
+    #[test]
+    fn test_next_prerelease() {
+        let res = next_prerelease(to_version("1.4.0"), "SNAPSHOT").unwrap();
+        assert_eq!(res.to_string(), "1.4.1-SNAPSHOT");
+        assert!(next_prerelease(to_version("1.4.0"), "not valid").is_err());
+    }
+
+    #[test]
+    fn test_next_development_version() {
+        let res = next_development_version(to_version("1.4.0"), VersionType::Patch, "0").unwrap();
+        assert_eq!(res.to_string(), "1.4.1-0");
+        let res = next_development_version(to_version("1.4.0"), VersionType::Minor, "dev").unwrap();
+        assert_eq!(res.to_string(), "1.5.0-dev");
+        let res =
+            next_development_version(to_version("1.4.0"), VersionType::Major, "SNAPSHOT").unwrap();
+        assert_eq!(res.to_string(), "2.0.0-SNAPSHOT");
+    }
+
+    #[test]
+    fn test_update_version_development() {
+        let res = update_version(to_version("1.5.0-dev"), VersionType::Minor).unwrap();
+        assert_eq!(res.to_string(), "1.5.0");
+        let res = update_version(to_version("2.0.0-dev"), VersionType::Major).unwrap();
+        assert_eq!(res.to_string(), "2.0.0");
+        let res = update_version(to_version("2.0.0-SNAPSHOT"), VersionType::Minor).unwrap();
+        assert_eq!(res.to_string(), "2.0.0");
+        let res = update_version(to_version("1.5.0-dev"), VersionType::Major).unwrap();
+        assert_eq!(res.to_string(), "2.0.0");
+    }
+
     #[test]
     fn test_update_version_patch() {
         let res = update_version(to_version("0.1.2"), VersionType::Patch).unwrap();
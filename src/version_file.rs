@@ -1,25 +1,104 @@
+use crate::config::VersionFileConfig;
+use crate::json_edit;
+use crate::xml_edit;
 use eyre::{Result, WrapErr};
+use regex::{Regex, RegexBuilder};
 use semver::Version;
 use std::fs;
+use std::ops::Range;
+use std::path::Path;
 use std::process::Command;
-use toml_edit::{value, Document};
+use toml_edit::{Document, Item, Value};
 
+static MAVEN_VERSION_PATH: &[&str] = &["project", "version"];
+static MSBUILD_VERSION_PATH: &[&str] = &["Project", "PropertyGroup", "Version"];
+static MSBUILD_PREFIX_PATH: &[&str] = &["Project", "PropertyGroup", "VersionPrefix"];
+static MSBUILD_SUFFIX_PATH: &[&str] = &["Project", "PropertyGroup", "VersionSuffix"];
+
+static GRADLE_VERSION_PATTERN: &str = r#"^\s*versionName\s*=?\s*["']([^"']+)["']"#;
+static GRADLE_VERSION_CODE_PATTERN: &str = r"^\s*versionCode\s*=?\s*(\d+)\s*$";
+static PROPERTIES_VERSION_PATTERN: &str = r"^\s*(?:version|versionName)\s*[=:]\s*(\S+)\s*$";
+static PROPERTIES_VERSION_CODE_PATTERN: &str = r"^\s*versionCode\s*[=:]\s*(\d+)\s*$";
+
+/// Lockfiles that can sit next to a `package.json`, in order of preference.
+static NPM_LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+];
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq)]
 pub enum VersionFiletype {
     TOML,
     JSON,
+    /// Maven `pom.xml`, uses `-SNAPSHOT` for development versions.
+    Maven,
+    /// .NET `.csproj` or `Directory.Build.props`.
+    MSBuild,
+    /// `build.gradle` or `build.gradle.kts`, the version is the `versionName`.
+    Gradle,
+    /// Java properties such as `gradle.properties`, the version is
+    /// `version` or `versionName`.
+    Properties,
+    /// Any text file, the version is the first capture group
+    /// (or the group named `version`) of the regex.
+    Pattern(String),
 }
 
 impl VersionFiletype {
     pub fn from_str(filename: &str) -> Result<Self> {
         let filename_lower = filename.to_lowercase();
-        let file_ext = filename_lower.split('.').last().unwrap();
+        let file_ext = filename_lower.split('.').next_back().unwrap();
         match file_ext {
             "toml" => Ok(VersionFiletype::TOML),
-            "json" => Ok(VersionFiletype::JSON),
+            "json" | "jsonc" => Ok(VersionFiletype::JSON),
+            "xml" if filename_lower.ends_with("pom.xml") => Ok(VersionFiletype::Maven),
+            "csproj" | "fsproj" | "vbproj" | "props" => Ok(VersionFiletype::MSBuild),
+            "gradle" => Ok(VersionFiletype::Gradle),
+            "kts" if filename_lower.ends_with(".gradle.kts") => Ok(VersionFiletype::Gradle),
+            "properties" => Ok(VersionFiletype::Properties),
             _ => Err(eyre!("Extension not supported")),
         }
     }
+
+    /// The key path of the version in TOML and JSON files.
+    pub fn default_key_path(&self) -> Vec<String> {
+        let key_path: &[&str] = match self {
+            VersionFiletype::TOML => &["package", "version"],
+            VersionFiletype::JSON => &["version"],
+            _ => &[],
+        };
+        key_path.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// The prerelease identifier used when beginning development on the next version.
+    pub fn prerelease_identifier(&self) -> &str {
+        match self {
+            VersionFiletype::Maven => "SNAPSHOT",
+            _ => "0",
+        }
+    }
+
+    /// The pattern locating the version in file types that are edited line by line.
+    fn version_pattern(&self) -> Option<&str> {
+        match self {
+            VersionFiletype::Gradle => Some(GRADLE_VERSION_PATTERN),
+            VersionFiletype::Properties => Some(PROPERTIES_VERSION_PATTERN),
+            VersionFiletype::Pattern(pattern) => Some(pattern),
+            _ => None,
+        }
+    }
+
+    /// The pattern locating the Android `versionCode`, if the file type has one.
+    fn version_code_pattern(&self) -> Option<&str> {
+        match self {
+            VersionFiletype::Gradle => Some(GRADLE_VERSION_CODE_PATTERN),
+            VersionFiletype::Properties => Some(PROPERTIES_VERSION_CODE_PATTERN),
+            _ => None,
+        }
+    }
 }
 
 trait ToVersion {
@@ -37,43 +116,148 @@ pub struct VersionFile {
     pub version_value: Version,
     pub version_filetype: VersionFiletype,
     pub lockfile: Option<String>,
+    /// Path to the version value in TOML and JSON files
+    pub key_path: Vec<String>,
+    /// Increment the integer `versionCode` every time the version is updated
+    pub bump_version_code: bool,
 }
 
 impl VersionFile {
     pub fn new(filename: &str) -> Result<Self> {
-        if !is_version_file_supported(&filename) {
+        if !is_version_file_supported(filename) {
             return Err(eyre!("The specified version file is not supported"));
         }
 
         let version_filetype = VersionFiletype::from_str(filename)?;
-        let version_value = read_version_file(&version_filetype, filename)?;
-        let lockfile = get_lockfile(&version_filetype);
+        VersionFile::with_filetype(filename, version_filetype)
+    }
+
+    /// Creates a version file from the CLI arguments or a package config.
+    pub fn from_config(config: &VersionFileConfig) -> Result<Self> {
+        let version_file = match (&config.pattern, &config.key) {
+            (Some(pattern), _) => VersionFile::with_filetype(
+                &config.file,
+                VersionFiletype::Pattern(pattern.to_owned()),
+            )?,
+            (None, Some(key)) => VersionFile::with_key_path(&config.file, key)?,
+            (None, None) => VersionFile::new(&config.file)?,
+        };
+
+        if config.version_code {
+            version_file.with_version_code()
+        } else {
+            Ok(version_file)
+        }
+    }
+
+    /// Creates a version file of the given type, regardless of its extension.
+    pub fn with_filetype(filename: &str, version_filetype: VersionFiletype) -> Result<Self> {
+        let key_path = version_filetype.default_key_path();
+        let lockfile = get_lockfile(&version_filetype, filename);
+
+        VersionFile::open(filename, version_filetype, key_path, lockfile)
+    }
+
+    /// Creates a TOML or JSON version file where the version is stored under `key`,
+    /// either a dotted path (`expo.version`) or a JSON Pointer (`/expo/version`).
+    pub fn with_key_path(filename: &str, key: &str) -> Result<Self> {
+        let version_filetype = VersionFiletype::from_str(filename)?;
+        if version_filetype.default_key_path().is_empty() {
+            return Err(eyre!("Key paths are only supported in TOML and JSON files"));
+        }
+
+        let key_path = parse_key_path(key)?;
+        // A lockfile only tracks the version of a package manifest
+        let lockfile = if key_path == version_filetype.default_key_path() {
+            get_lockfile(&version_filetype, filename)
+        } else {
+            None
+        };
+
+        VersionFile::open(filename, version_filetype, key_path, lockfile)
+    }
+
+    fn open(
+        filename: &str,
+        version_filetype: VersionFiletype,
+        key_path: Vec<String>,
+        lockfile: Option<String>,
+    ) -> Result<Self> {
+        let version_value = read_version_file(&version_filetype, filename, &key_path)
+            .wrap_err_with(|| format!("Could not read the version from {}", filename))?;
 
         Ok(VersionFile {
             filename: filename.to_owned(),
             version_value,
             version_filetype,
             lockfile,
+            key_path,
+            bump_version_code: false,
         })
     }
 
+    /// Enables bumping the `versionCode` along with the version,
+    /// which the Play Store requires for every upload.
+    pub fn with_version_code(mut self) -> Result<Self> {
+        let pattern = match self.version_filetype.version_code_pattern() {
+            Some(pattern) => pattern,
+            None => {
+                return Err(eyre!(
+                    "versionCode is only supported in Gradle and properties files"
+                ))
+            }
+        };
+        let ver_file = fs::read_to_string(&self.filename)?;
+        find_pattern_version(&ver_file, pattern).wrap_err("No versionCode found")?;
+
+        self.bump_version_code = true;
+        Ok(self)
+    }
+
     /// Updates the version file with the new version value
     pub fn update_version_file(&mut self, new_ver: &Version) -> Result<()> {
         let ver_file = fs::read_to_string(&self.filename)?;
 
-        match self.version_filetype {
+        match &self.version_filetype {
             VersionFiletype::TOML => {
-                let mut doc = ver_file.parse::<Document>().expect("invalid doc");
-                doc["package"]["version"] = value(new_ver.to_string());
+                let mut doc = ver_file.parse::<Document>()?;
+                let item = toml_item_mut(doc.as_item_mut(), &self.key_path)?;
+                set_toml_string(item, &new_ver.to_string());
 
                 fs::write(&self.filename, doc.to_string())?;
-                sync_cargo_lockfile()?;
+                if self.lockfile.is_some() {
+                    sync_cargo_lockfile()?;
+                }
             }
             VersionFiletype::JSON => {
-                let mut v: serde_json::Value = serde_json::from_str(&ver_file)?;
-                v["version"] = serde_json::to_value(&new_ver.to_string())?;
+                let version_file_contents =
+                    json_edit::replace_string(&ver_file, &self.key_path, &new_ver.to_string())?;
+                fs::write(&self.filename, version_file_contents)?;
+
+                if let Some(lockfile) = &self.lockfile {
+                    let name = json_edit::read_string(&ver_file, &["name"])?.unwrap_or_default();
+                    sync_npm_lockfile(lockfile, &name, &self.version_value, new_ver)?;
+                }
+            }
+            VersionFiletype::Maven => {
+                let version_file_contents =
+                    xml_edit::replace_text(&ver_file, MAVEN_VERSION_PATH, &new_ver.to_string())?;
+                fs::write(&self.filename, version_file_contents)?;
+            }
+            VersionFiletype::MSBuild => {
+                let version_file_contents = update_msbuild_version(&ver_file, new_ver)?;
+                fs::write(&self.filename, version_file_contents)?;
+            }
+            VersionFiletype::Gradle | VersionFiletype::Properties | VersionFiletype::Pattern(_) => {
+                let pattern = self.version_filetype.version_pattern().unwrap();
+                let mut version_file_contents =
+                    replace_pattern_version(&ver_file, pattern, &new_ver.to_string())?;
+
+                if self.bump_version_code {
+                    let pattern = self.version_filetype.version_code_pattern().unwrap();
+                    version_file_contents = bump_version_code(&version_file_contents, pattern)?;
+                }
 
-                let version_file_contents = format!("{}\n", serde_json::to_string_pretty(&v)?);
                 fs::write(&self.filename, version_file_contents)?;
             }
         };
@@ -96,45 +280,265 @@ impl VersionFile {
     }
 }
 
-pub fn read_version_file(version_filetype: &VersionFiletype, file_path: &str) -> Result<Version> {
+/// Reads the version from the version file. `key_path` locates the version
+/// in TOML and JSON files and is ignored for other file types.
+pub fn read_version_file(
+    version_filetype: &VersionFiletype,
+    file_path: &str,
+    key_path: &[String],
+) -> Result<Version> {
     match version_filetype {
         VersionFiletype::TOML => {
             let ver_file = fs::read_to_string(file_path)?;
-            let doc = ver_file.parse::<Document>().expect("invalid doc");
-
-            let package_info = doc.get("package");
+            let mut doc = ver_file.parse::<Document>()?;
 
-            match package_info {
-                Some(package_info) => match package_info.get("version") {
-                    Some(ver) => ver.as_str().unwrap().to_version(),
-                    None => Err(eyre!("Version property is not valid")),
-                },
-                None => Err(eyre!("No package property found in TOML file")),
+            match toml_item_mut(doc.as_item_mut(), key_path)?.as_str() {
+                Some(ver) => ver.to_version(),
+                None => Err(eyre!("'{}' is not a string", key_path.join("."))),
             }
         }
         VersionFiletype::JSON => {
             let ver_file = fs::read_to_string(file_path)?;
-            let v: serde_json::Value = serde_json::from_str(&ver_file)?;
 
-            match v.get("version") {
-                Some(ver) => ver.as_str().unwrap().to_version(),
-                None => Err(eyre!("Version property is not valid")),
+            match json_edit::read_string(&ver_file, key_path)? {
+                Some(ver) => ver.to_version(),
+                None => Err(eyre!("No version found at '{}'", key_path.join("."))),
             }
         }
+        VersionFiletype::Maven => {
+            let ver_file = fs::read_to_string(file_path)?;
+
+            match xml_edit::read_text(&ver_file, MAVEN_VERSION_PATH)? {
+                Some(ver) => ver.to_version(),
+                None => Err(eyre!("No <version> element found in the <project>")),
+            }
+        }
+        VersionFiletype::MSBuild => {
+            let ver_file = fs::read_to_string(file_path)?;
+            read_msbuild_version(&ver_file)
+        }
+        VersionFiletype::Gradle | VersionFiletype::Properties | VersionFiletype::Pattern(_) => {
+            let ver_file = fs::read_to_string(file_path)?;
+            let pattern = version_filetype.version_pattern().unwrap();
+            let span = find_pattern_version(&ver_file, pattern)?;
+
+            ver_file[span].to_version()
+        }
+    }
+}
+
+/// Reads `<Version>`, or `<VersionPrefix>` and `<VersionSuffix>` when
+/// the version is split in two.
+fn read_msbuild_version(contents: &str) -> Result<Version> {
+    if let Some(ver) = xml_edit::read_text(contents, MSBUILD_VERSION_PATH)? {
+        return ver.to_version();
+    }
+
+    let prefix = match xml_edit::read_text(contents, MSBUILD_PREFIX_PATH)? {
+        Some(prefix) => prefix,
+        None => return Err(eyre!("No <Version> or <VersionPrefix> element found")),
+    };
+    match xml_edit::read_text(contents, MSBUILD_SUFFIX_PATH)? {
+        Some(suffix) if !suffix.is_empty() => format!("{}-{}", prefix, suffix).to_version(),
+        _ => prefix.to_version(),
+    }
+}
+
+/// Writes the version to `<Version>` when it exists, otherwise splits it
+/// between `<VersionPrefix>` and `<VersionSuffix>`.
+fn update_msbuild_version(contents: &str, new_ver: &Version) -> Result<String> {
+    if xml_edit::find_text(contents, MSBUILD_VERSION_PATH)?.is_some() {
+        return xml_edit::replace_text(contents, MSBUILD_VERSION_PATH, &new_ver.to_string());
+    }
+
+    let prefix = format!("{}.{}.{}", new_ver.major, new_ver.minor, new_ver.patch);
+    let suffix = new_ver.pre.as_str();
+    let contents = xml_edit::replace_text(contents, MSBUILD_PREFIX_PATH, &prefix)?;
+
+    if xml_edit::find_text(&contents, MSBUILD_SUFFIX_PATH)?.is_some() {
+        xml_edit::replace_text(&contents, MSBUILD_SUFFIX_PATH, suffix)
+    } else if !suffix.is_empty() {
+        xml_edit::insert_after(&contents, MSBUILD_PREFIX_PATH, "VersionSuffix", suffix)
+    } else {
+        Ok(contents)
+    }
+}
+
+/// Parses a dotted key path (`expo.version`) or a JSON Pointer (`/expo/version`).
+pub fn parse_key_path(key: &str) -> Result<Vec<String>> {
+    let key_path: Vec<String> = match key.strip_prefix('/') {
+        Some(pointer) => pointer
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None => key.split('.').map(String::from).collect(),
+    };
+
+    if key.is_empty() || key_path.iter().any(|segment| segment.is_empty()) {
+        return Err(eyre!("Invalid key path '{}'", key));
     }
+    Ok(key_path)
+}
+
+/// Walks the TOML document down the key path, through tables and inline tables.
+fn toml_item_mut<'a>(item: &'a mut Item, key_path: &[String]) -> Result<&'a mut Item> {
+    key_path.iter().try_fold(item, |item, key| {
+        item.get_mut(key.as_str())
+            .ok_or_else(|| eyre!("No version found at '{}'", key_path.join(".")))
+    })
+}
+
+/// Replaces a TOML value with a string, keeping the whitespace and comments around it.
+pub fn set_toml_string(item: &mut Item, new_value: &str) {
+    let decor = item.as_value().map(|v| v.decor().to_owned());
+
+    let mut new_value = Value::from(new_value);
+    if let Some(decor) = decor {
+        *new_value.decor_mut() = decor;
+    }
+    *item = Item::Value(new_value);
+}
+
+/// Replaces the version captured by the pattern with `new_value`.
+fn replace_pattern_version(contents: &str, pattern: &str, new_value: &str) -> Result<String> {
+    let span = find_pattern_version(contents, pattern)?;
+    let mut updated_contents = contents.to_owned();
+    updated_contents.replace_range(span, new_value);
+
+    Ok(updated_contents)
+}
+
+/// Increments the integer `versionCode` captured by the pattern.
+fn bump_version_code(contents: &str, pattern: &str) -> Result<String> {
+    let span = find_pattern_version(contents, pattern)?;
+    let version_code = contents[span]
+        .parse::<u64>()
+        .wrap_err("Invalid versionCode")?;
+    debug!("Bump versionCode to {}", version_code + 1);
+
+    replace_pattern_version(contents, pattern, &(version_code + 1).to_string())
+}
+
+/// Returns the location of the version captured by the pattern.
+/// `^` and `$` match at line boundaries.
+fn find_pattern_version(contents: &str, pattern: &str) -> Result<Range<usize>> {
+    let re: Regex = RegexBuilder::new(pattern)
+        .multi_line(true)
+        .build()
+        .wrap_err("Invalid version pattern")?;
+    if re.captures_len() < 2 {
+        return Err(eyre!("Version pattern needs a capture group"));
+    }
+
+    let captures = re.captures_iter(contents).collect::<Vec<_>>();
+    let captures = match captures.as_slice() {
+        [captures] => captures,
+        [] => return Err(eyre!("Version pattern did not match")),
+        _ => {
+            return Err(eyre!(
+                "Version pattern matched {} times, expected once",
+                captures.len()
+            ))
+        }
+    };
+
+    captures
+        .name("version")
+        .or_else(|| captures.get(1))
+        .map(|m| m.range())
+        .ok_or_else(|| eyre!("Version pattern did not capture a version"))
 }
 
 /// Returns true if the version file is supported
 /// and false otherwise.
 fn is_version_file_supported(version_file: &str) -> bool {
-    version_file.ends_with(".toml") || version_file.ends_with(".json")
+    VersionFiletype::from_str(version_file).is_ok()
 }
 
-pub fn get_lockfile(version_filetype: &VersionFiletype) -> Option<String> {
+/// Returns the lockfile that has to be kept in sync with the version file.
+/// JSON version files look for an npm, yarn or pnpm lockfile in the same directory.
+pub fn get_lockfile(version_filetype: &VersionFiletype, filename: &str) -> Option<String> {
     match version_filetype {
         VersionFiletype::TOML => Some("Cargo.lock".to_string()),
-        VersionFiletype::JSON => None,
+        VersionFiletype::JSON => {
+            let dir = Path::new(filename)
+                .parent()
+                .unwrap_or_else(|| Path::new(""));
+            NPM_LOCKFILES
+                .iter()
+                .map(|lockfile| dir.join(lockfile))
+                .find(|path| path.is_file())
+                .map(|path| path.to_string_lossy().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Writes the new root package version to an npm, yarn or pnpm lockfile.
+pub fn sync_npm_lockfile(
+    lockfile: &str,
+    name: &str,
+    old_ver: &Version,
+    new_ver: &Version,
+) -> Result<()> {
+    debug!("Sync {}", lockfile);
+    let contents = fs::read_to_string(lockfile)?;
+
+    let updated_contents = if lockfile.ends_with(".json") {
+        update_package_lock(&contents, new_ver)?
+    } else {
+        update_workspace_lock(&contents, name, old_ver, new_ver)
+    };
+
+    fs::write(lockfile, updated_contents)?;
+    Ok(())
+}
+
+/// Updates the root `version` and `packages[""].version` of a
+/// `package-lock.json` or `npm-shrinkwrap.json`.
+fn update_package_lock(contents: &str, new_ver: &Version) -> Result<String> {
+    let new_ver = new_ver.to_string();
+    let mut updated_contents = json_edit::replace_string(contents, &["version"], &new_ver)?;
+
+    let root_package_path = ["packages", "", "version"];
+    if json_edit::find_value(&updated_contents, &root_package_path)?.is_some() {
+        updated_contents =
+            json_edit::replace_string(&updated_contents, &root_package_path, &new_ver)?;
+    }
+
+    Ok(updated_contents)
+}
+
+/// yarn and pnpm lockfiles only embed the root version in the workspace entry
+/// of the root package (e.g. `"name@workspace:.":`), so only that entry is
+/// updated and only when it still holds the old version.
+fn update_workspace_lock(
+    contents: &str,
+    name: &str,
+    old_ver: &Version,
+    new_ver: &Version,
+) -> String {
+    let workspace_key = format!("{}@workspace:.", name);
+    let old_line = format!("version: {}", old_ver);
+    let mut in_root_entry = false;
+
+    let mut lines = contents
+        .lines()
+        .map(|line| {
+            if !line.starts_with(' ') {
+                in_root_entry = !name.is_empty() && line.contains(&workspace_key);
+            } else if in_root_entry && line.trim() == old_line {
+                return line.replace(&old_line, &format!("version: {}", new_ver));
+            }
+            line.to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    if contents.ends_with('\n') {
+        lines.push('\n');
     }
+    lines
 }
 
 // I hate doing 'cargo check' here as its super slow when it has to fetch packages.
@@ -142,11 +546,16 @@ pub fn get_lockfile(version_filetype: &VersionFiletype) -> Option<String> {
 // https://internals.rust-lang.org/t/pre-rfc-cargo-command-to-just-sync-lockfile/13119
 pub fn sync_cargo_lockfile() -> Result<bool> {
     debug!("Sync Cargo.lock");
-    let output = Command::new("cargo").args(&["check"]).output()?;
+    let output = Command::new("cargo").args(["check"]).output()?;
     Ok(output.status.success())
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::bool_assert_comparison,
+    clippy::bool_comparison,
+    clippy::needless_borrows_for_generic_args
+)]
 mod tests {
     use super::*;
 
@@ -168,7 +577,29 @@ mod tests {
             VersionFiletype::from_str("version.toml").unwrap(),
             VersionFiletype::TOML
         );
+        assert_eq!(
+            VersionFiletype::from_str("service/pom.xml").unwrap(),
+            VersionFiletype::Maven
+        );
+        assert_eq!(
+            VersionFiletype::from_str("Api.csproj").unwrap(),
+            VersionFiletype::MSBuild
+        );
+        assert_eq!(
+            VersionFiletype::from_str("Directory.Build.props").unwrap(),
+            VersionFiletype::MSBuild
+        );
+        assert_eq!(
+            VersionFiletype::from_str("app/build.gradle.kts").unwrap(),
+            VersionFiletype::Gradle
+        );
+        assert_eq!(
+            VersionFiletype::from_str("gradle.properties").unwrap(),
+            VersionFiletype::Properties
+        );
         assert!(VersionFiletype::from_str("version.txt").is_err(),);
+        assert!(VersionFiletype::from_str("script.kts").is_err(),);
+        assert!(VersionFiletype::from_str("config.xml").is_err(),);
     }
 
     #[test]
@@ -244,15 +675,34 @@ serde = { version = "1.0.136", features = ["derive"] }
         assert_eq!(v.get_version_value().to_string(), "0.2.6");
         assert_eq!(
             updated_contents,
-            r#"{
-  "name": "testing",
-  "version": "0.2.6",
-  "author": "me"
-}
-"#
+            r#"
+            {
+                "name": "testing",
+                "version": "0.2.6",
+                "author": "me"
+            }
+        "#
         );
     }
 
+    #[test]
+    fn test_update_version_file_jsonc() {
+        let test_file = "test.jsonc";
+        let contents =
+            "{\n\t// deno.json style comment\n\t\"version\": \"1.2.3\",\n\t\"tasks\": {},\n}\n";
+
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::new(test_file).unwrap();
+        v.update_version_file(&Version::parse("1.3.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(updated_contents, contents.replace("1.2.3", "1.3.0"));
+    }
+
     #[test]
     fn test_read_version_file_package_json_invalid() {
         let test_file = "invalid.json";
@@ -266,7 +716,7 @@ serde = { version = "1.0.136", features = ["derive"] }
 
         fs::write(test_file, contents).unwrap();
 
-        let v = read_version_file(&VersionFiletype::JSON, test_file);
+        let v = read_version_file(&VersionFiletype::JSON, test_file, &["version".to_string()]);
         assert!(v.is_err());
         fs::remove_file(&test_file).unwrap();
     }
@@ -299,6 +749,8 @@ serde = { version = "1.0.136", features = ["derive"] }
             version_value: "0.1.2".to_version().unwrap(),
             version_filetype: VersionFiletype::TOML,
             lockfile: Some("Cargo.lock".to_string()),
+            key_path: VersionFiletype::TOML.default_key_path(),
+            bump_version_code: false,
         };
 
         assert_eq!(
@@ -314,8 +766,349 @@ serde = { version = "1.0.136", features = ["derive"] }
             version_value: "0.1.2".to_version().unwrap(),
             version_filetype: VersionFiletype::TOML,
             lockfile: None,
+            key_path: VersionFiletype::JSON.default_key_path(),
+            bump_version_code: false,
         };
 
         assert_eq!(ver_file.get_tracked_files(), vec!["package.json"]);
     }
+
+    #[test]
+    fn test_get_lockfile_npm() {
+        let test_dir = "lockfile_test";
+        fs::create_dir_all(test_dir).unwrap();
+        let version_file = format!("{}/package.json", test_dir);
+
+        assert_eq!(get_lockfile(&VersionFiletype::JSON, &version_file), None);
+
+        fs::write(format!("{}/package-lock.json", test_dir), "{}").unwrap();
+        let lockfile = get_lockfile(&VersionFiletype::JSON, &version_file);
+        fs::remove_dir_all(test_dir).unwrap();
+
+        assert_eq!(
+            lockfile,
+            Some("lockfile_test/package-lock.json".to_string())
+        );
+    }
+
+    #[test]
+    fn test_update_package_lock() {
+        let contents = r#"{
+  "name": "testing",
+  "version": "0.2.5",
+  "lockfileVersion": 3,
+  "packages": {
+    "": {
+      "name": "testing",
+      "version": "0.2.5"
+    },
+    "node_modules/dep": {
+      "version": "0.2.5"
+    }
+  }
+}
+"#;
+
+        let res = update_package_lock(contents, &Version::parse("0.2.6").unwrap()).unwrap();
+        assert_eq!(
+            res,
+            r#"{
+  "name": "testing",
+  "version": "0.2.6",
+  "lockfileVersion": 3,
+  "packages": {
+    "": {
+      "name": "testing",
+      "version": "0.2.6"
+    },
+    "node_modules/dep": {
+      "version": "0.2.5"
+    }
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_update_workspace_lock() {
+        let contents = r#"__metadata:
+  version: 6
+
+"dep@npm:0.2.5":
+  version: 0.2.5
+
+"testing@workspace:.":
+  version: 0.2.5
+  languageName: unknown
+"#;
+        let old_ver = Version::parse("0.2.5").unwrap();
+        let new_ver = Version::parse("0.2.6").unwrap();
+
+        let res = update_workspace_lock(contents, "testing", &old_ver, &new_ver);
+        assert_eq!(
+            res,
+            contents.replace("  version: 0.2.5\n  lang", "  version: 0.2.6\n  lang")
+        );
+
+        let res = update_workspace_lock(contents, "other", &old_ver, &new_ver);
+        assert_eq!(res, contents);
+    }
+
+    #[test]
+    fn test_update_version_file_pattern() {
+        let test_file = "version_test.rs";
+        let contents = r#"/// The current version
+pub const VERSION: &str = "0.4.1";
+pub const MIN_VERSION: &str = "0.1.0";
+"#;
+
+        fs::write(test_file, contents).unwrap();
+
+        let pattern = r#"^pub const VERSION: &str = "(.+)";$"#.to_string();
+        let mut v =
+            VersionFile::with_filetype(test_file, VersionFiletype::Pattern(pattern)).unwrap();
+        assert_eq!(v.get_version_value().to_string(), "0.4.1");
+
+        v.update_version_file(&Version::parse("0.5.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(updated_contents, contents.replace("0.4.1", "0.5.0"));
+    }
+
+    #[test]
+    fn test_find_pattern_version() {
+        let contents = "__version__ = '1.2.3'\n";
+        assert_eq!(
+            find_pattern_version(contents, r"__version__ = '(?P<version>[^']+)'").unwrap(),
+            15..20
+        );
+        assert_eq!(find_pattern_version("1.2.3\n", r"^(\S+)$").unwrap(), 0..5);
+
+        assert!(find_pattern_version(contents, r"__version__").is_err());
+        assert!(find_pattern_version(contents, r"VERSION = '(.+)'").is_err());
+        assert!(find_pattern_version("1.2.3\n1.2.4\n", r"^(\S+)$").is_err());
+    }
+
+    #[test]
+    fn test_update_version_file_pom_xml() {
+        let test_dir = "pom_test";
+        let test_file = "pom_test/pom.xml";
+        let contents = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project>
+    <parent>
+        <version>2.7.5</version>
+    </parent>
+    <artifactId>service</artifactId>
+    <version>1.4.0-SNAPSHOT</version>
+</project>
+"#;
+
+        fs::create_dir_all(test_dir).unwrap();
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::new(test_file).unwrap();
+        assert_eq!(v.get_version_value().to_string(), "1.4.0-SNAPSHOT");
+
+        v.update_version_file(&Version::parse("1.4.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_dir_all(test_dir).unwrap();
+
+        assert_eq!(
+            updated_contents,
+            contents.replace("1.4.0-SNAPSHOT", "1.4.0")
+        );
+        assert_eq!(v.version_filetype.prerelease_identifier(), "SNAPSHOT");
+    }
+
+    #[test]
+    fn test_update_msbuild_version() {
+        let contents = r#"<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <TargetFramework>net6.0</TargetFramework>
+  </PropertyGroup>
+  <PropertyGroup>
+    <VersionPrefix>1.2.0</VersionPrefix>
+  </PropertyGroup>
+</Project>
+"#;
+        assert_eq!(read_msbuild_version(contents).unwrap().to_string(), "1.2.0");
+
+        let prerelease =
+            update_msbuild_version(contents, &"1.2.1-0".to_version().unwrap()).unwrap();
+        assert_eq!(
+            prerelease,
+            contents.replace(
+                "<VersionPrefix>1.2.0</VersionPrefix>",
+                "<VersionPrefix>1.2.1</VersionPrefix>\n    <VersionSuffix>0</VersionSuffix>"
+            )
+        );
+        assert_eq!(
+            read_msbuild_version(&prerelease).unwrap().to_string(),
+            "1.2.1-0"
+        );
+
+        let release = update_msbuild_version(&prerelease, &"1.3.0".to_version().unwrap()).unwrap();
+        assert_eq!(
+            release,
+            contents.replace(
+                "<VersionPrefix>1.2.0</VersionPrefix>",
+                "<VersionPrefix>1.3.0</VersionPrefix>\n    <VersionSuffix></VersionSuffix>"
+            )
+        );
+
+        let contents = "<Project>\n  <PropertyGroup>\n    <Version>1.0.0</Version>\n  </PropertyGroup>\n</Project>\n";
+        let res = update_msbuild_version(contents, &"1.1.0".to_version().unwrap()).unwrap();
+        assert_eq!(res, contents.replace("1.0.0", "1.1.0"));
+    }
+
+    #[test]
+    fn test_update_version_file_build_gradle() {
+        let test_file = "build_test.gradle";
+        let contents = r#"android {
+    defaultConfig {
+        applicationId "com.example.app"
+        minSdkVersion 21
+        versionCode 41
+        versionName "2.3.0-0"
+    }
+}
+"#;
+
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::new(test_file)
+            .and_then(|v| v.with_version_code())
+            .unwrap();
+        assert_eq!(v.get_version_value().to_string(), "2.3.0-0");
+
+        v.update_version_file(&Version::parse("2.3.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(
+            updated_contents,
+            contents
+                .replace("versionCode 41", "versionCode 42")
+                .replace("\"2.3.0-0\"", "\"2.3.0\"")
+        );
+    }
+
+    #[test]
+    fn test_update_version_file_gradle_properties() {
+        let test_file = "gradle_test.properties";
+        let contents = "org.gradle.jvmargs=-Xmx2048m\nversion = 1.2.3\n";
+
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::new(test_file).unwrap();
+        assert!(VersionFile::new(test_file)
+            .and_then(|v| v.with_version_code())
+            .is_err());
+
+        v.update_version_file(&Version::parse("1.3.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(
+            updated_contents,
+            "org.gradle.jvmargs=-Xmx2048m\nversion = 1.3.0\n"
+        );
+    }
+
+    #[test]
+    fn test_bump_version_code() {
+        let contents = "versionName=1.0.0\nversionCode=9\n";
+        assert_eq!(
+            bump_version_code(contents, PROPERTIES_VERSION_CODE_PATTERN).unwrap(),
+            "versionName=1.0.0\nversionCode=10\n"
+        );
+
+        let contents = "versionCode = 99\n";
+        assert_eq!(
+            bump_version_code(contents, GRADLE_VERSION_CODE_PATTERN).unwrap(),
+            "versionCode = 100\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_key_path() {
+        assert_eq!(parse_key_path("version").unwrap(), vec!["version"]);
+        assert_eq!(
+            parse_key_path("expo.version").unwrap(),
+            vec!["expo", "version"]
+        );
+        assert_eq!(
+            parse_key_path("/extra/a~1b/0").unwrap(),
+            vec!["extra", "a/b", "0"]
+        );
+
+        assert!(parse_key_path("").is_err());
+        assert!(parse_key_path("expo..version").is_err());
+        assert!(parse_key_path("/").is_err());
+    }
+
+    #[test]
+    fn test_update_version_file_json_key_path() {
+        let test_file = "app_test.json";
+        let contents = r#"{
+    "version": "not-semver",
+    "expo": {
+        "name": "app",
+        "version": "3.1.0"
+    }
+}
+"#;
+
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::with_key_path(test_file, "expo.version").unwrap();
+        assert!(VersionFile::with_key_path(test_file, "/expo/missing").is_err());
+
+        v.update_version_file(&Version::parse("3.2.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(v.lockfile, None);
+        assert_eq!(updated_contents, contents.replace("3.1.0", "3.2.0"));
+    }
+
+    #[test]
+    fn test_update_version_file_toml_key_path() {
+        let test_file = "pyproject_test.toml";
+        let contents = r#"[tool.poetry]
+name = "service"
+version = "0.9.0" # bumped on release
+meta = { build = { version = "0.9.0" } }
+"#;
+
+        fs::write(test_file, contents).unwrap();
+
+        let mut v = VersionFile::with_key_path(test_file, "tool.poetry.version").unwrap();
+        let nested = VersionFile::with_key_path(test_file, "tool.poetry.meta.build.version");
+        assert_eq!(nested.unwrap().get_version_value().to_string(), "0.9.0");
+        assert!(VersionFile::with_key_path(test_file, "package.version").is_err());
+
+        v.update_version_file(&Version::parse("1.0.0").unwrap())
+            .unwrap();
+
+        let updated_contents = fs::read_to_string(test_file).unwrap();
+        fs::remove_file(test_file).unwrap();
+
+        assert_eq!(
+            updated_contents,
+            contents.replace("\"0.9.0\" #", "\"1.0.0\" #")
+        );
+    }
 }
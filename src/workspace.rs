@@ -0,0 +1,436 @@
+use crate::config::{Config, PackageConfig};
+use crate::json_edit;
+use crate::version_file::set_toml_string;
+use eyre::Result;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use toml_edit::Document;
+
+static CARGO_DEPENDENCY_TABLES: &[&str] =
+    &["dependencies", "dev-dependencies", "build-dependencies"];
+static NPM_DEPENDENCY_FIELDS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ManifestKind {
+    Cargo,
+    Npm,
+}
+
+/// The `Cargo.toml` or `package.json` of a package, used to find
+/// dependencies between the packages of a monorepo.
+#[derive(Debug)]
+pub struct Manifest {
+    pub path: String,
+    pub kind: ManifestKind,
+    /// The name other packages depend on, which may differ from the name in the config
+    pub name: String,
+    pub dependencies: Vec<String>,
+}
+
+impl Manifest {
+    /// Reads the manifest in the package directory, `None` when there is none.
+    pub fn read(dir: &str) -> Result<Option<Self>> {
+        let cargo_toml = Path::new(dir).join("Cargo.toml");
+        let package_json = Path::new(dir).join("package.json");
+
+        if cargo_toml.is_file() {
+            let path = cargo_toml.to_string_lossy().to_string();
+            Manifest::parse(&path, ManifestKind::Cargo, &fs::read_to_string(&path)?).map(Some)
+        } else if package_json.is_file() {
+            let path = package_json.to_string_lossy().to_string();
+            Manifest::parse(&path, ManifestKind::Npm, &fs::read_to_string(&path)?).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse(path: &str, kind: ManifestKind, contents: &str) -> Result<Self> {
+        let (name, dependencies) = match kind {
+            ManifestKind::Cargo => {
+                let doc = contents.parse::<Document>()?;
+                let name = doc
+                    .get("package")
+                    .and_then(|package| package.get("name"))
+                    .and_then(|name| name.as_str())
+                    .map(String::from);
+
+                let mut dependencies = vec![];
+                for table in CARGO_DEPENDENCY_TABLES {
+                    if let Some(table) = doc.get(table).and_then(|t| t.as_table_like()) {
+                        for (key, item) in table.iter() {
+                            // Renamed dependencies point to the real name with `package`
+                            let name = item.get("package").and_then(|p| p.as_str());
+                            dependencies.push(name.unwrap_or(key).to_string());
+                        }
+                    }
+                }
+                (name, dependencies)
+            }
+            ManifestKind::Npm => {
+                let v: serde_json::Value = serde_json::from_str(contents)?;
+                let name = v["name"].as_str().map(String::from);
+
+                let dependencies = NPM_DEPENDENCY_FIELDS
+                    .iter()
+                    .filter_map(|field| v[field].as_object())
+                    .flat_map(|deps| deps.keys().cloned())
+                    .collect();
+                (name, dependencies)
+            }
+        };
+
+        Ok(Manifest {
+            path: path.to_owned(),
+            kind,
+            name: name.ok_or_else(|| eyre!("No package name found in {}", path))?,
+            dependencies,
+        })
+    }
+
+    pub fn depends_on(&self, name: &str) -> bool {
+        self.dependencies.iter().any(|dep| dep == name)
+    }
+
+    /// Points every requirement on the dependency to the new version,
+    /// keeping the range operator (`^1.3.0` becomes `^1.4.0`).
+    /// Requirements like `workspace:*` or path-only dependencies are left alone.
+    pub fn update_dependency(&self, name: &str, new_ver: &Version) -> Result<()> {
+        let contents = fs::read_to_string(&self.path)?;
+        let updated_contents = match self.kind {
+            ManifestKind::Cargo => update_cargo_dependency(&contents, name, new_ver)?,
+            ManifestKind::Npm => update_npm_dependency(&contents, name, new_ver)?,
+        };
+
+        fs::write(&self.path, updated_contents)?;
+        Ok(())
+    }
+}
+
+/// A package from the config and its manifest.
+#[derive(Debug)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub manifest: Option<Manifest>,
+}
+
+/// The internal dependency graph of a monorepo.
+pub struct Workspace {
+    pub packages: Vec<WorkspacePackage>,
+}
+
+impl Workspace {
+    pub fn load(config: &Config) -> Result<Self> {
+        let packages = config
+            .packages
+            .iter()
+            .map(|package: &PackageConfig| {
+                Ok(WorkspacePackage {
+                    name: package.name.to_owned(),
+                    manifest: Manifest::read(&package.path)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Workspace { packages })
+    }
+
+    pub fn package(&self, name: &str) -> Option<&WorkspacePackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+
+    /// Names of the workspace packages the package depends on directly.
+    fn dependencies(&self, package: &WorkspacePackage) -> Vec<&str> {
+        let manifest = match &package.manifest {
+            Some(manifest) => manifest,
+            None => return vec![],
+        };
+
+        self.packages
+            .iter()
+            .filter(|other| other.name != package.name)
+            .filter(|other| match &other.manifest {
+                Some(other_manifest) => manifest.depends_on(&other_manifest.name),
+                None => false,
+            })
+            .map(|other| other.name.as_str())
+            .collect()
+    }
+
+    /// Returns the named packages, plus every package depending on them directly
+    /// or transitively when `with_dependents` is set, in the order they have to be released.
+    pub fn release_order(
+        &self,
+        names: &[&str],
+        with_dependents: bool,
+    ) -> Result<Vec<&WorkspacePackage>> {
+        let dependencies: HashMap<&str, Vec<&str>> = self
+            .packages
+            .iter()
+            .map(|package| (package.name.as_str(), self.dependencies(package)))
+            .collect();
+
+        // Collect all packages that are affected by the release
+        let mut affected: HashSet<&str> = names.iter().copied().collect();
+        let mut queue = names.to_vec();
+        while let Some(current) = queue.pop() {
+            if !with_dependents {
+                break;
+            }
+            for package in &self.packages {
+                let deps = &dependencies[package.name.as_str()];
+                if deps.contains(&current) && affected.insert(&package.name) {
+                    queue.push(&package.name);
+                }
+            }
+        }
+
+        // Topological sort, a package is released after all of its affected dependencies
+        let mut ordered: Vec<&WorkspacePackage> = vec![];
+        let mut released: HashSet<&str> = HashSet::new();
+        while ordered.len() < affected.len() {
+            let next = self.packages.iter().find(|package| {
+                affected.contains(package.name.as_str())
+                    && !released.contains(package.name.as_str())
+                    && dependencies[package.name.as_str()]
+                        .iter()
+                        .all(|dep| !affected.contains(dep) || released.contains(dep))
+            });
+
+            match next {
+                Some(package) => {
+                    released.insert(&package.name);
+                    ordered.push(package);
+                }
+                None => {
+                    return Err(eyre!(
+                        "Circular dependency between the packages depending on {}",
+                        names.join(", ")
+                    ))
+                }
+            }
+        }
+
+        Ok(ordered)
+    }
+}
+
+/// Replaces the version in a requirement, keeping the operator in front of it.
+/// Returns `None` for requirements that do not pin a version.
+fn bump_requirement(requirement: &str, new_ver: &Version) -> Option<String> {
+    let version_start = requirement.find(|c: char| c.is_ascii_digit())?;
+    let (operator, version) = requirement.split_at(version_start);
+
+    let is_operator = operator
+        .trim_start_matches("workspace:")
+        .chars()
+        .all(|c| matches!(c, '^' | '~' | '=' | '>' | '<' | ' '));
+    let is_version = version
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    if !is_operator || !is_version {
+        return None;
+    }
+
+    Some(format!("{}{}", operator, new_ver))
+}
+
+fn update_npm_dependency(contents: &str, name: &str, new_ver: &Version) -> Result<String> {
+    let mut updated_contents = contents.to_owned();
+
+    for field in NPM_DEPENDENCY_FIELDS {
+        let path = [*field, name];
+        if let Some(requirement) = json_edit::read_string(&updated_contents, &path)? {
+            if let Some(requirement) = bump_requirement(&requirement, new_ver) {
+                updated_contents =
+                    json_edit::replace_string(&updated_contents, &path, &requirement)?;
+            }
+        }
+    }
+
+    Ok(updated_contents)
+}
+
+fn update_cargo_dependency(contents: &str, name: &str, new_ver: &Version) -> Result<String> {
+    let mut doc = contents.parse::<Document>()?;
+
+    for table in CARGO_DEPENDENCY_TABLES {
+        let table = match doc.get_mut(table).and_then(|t| t.as_table_like_mut()) {
+            Some(table) => table,
+            None => continue,
+        };
+
+        for (key, item) in table.iter_mut() {
+            let package = item.get("package").and_then(|p| p.as_str());
+            if package.unwrap_or(key.get()) != name {
+                continue;
+            }
+
+            // Either `dep = "1.2"` or `dep = { version = "1.2", path = ".." }`
+            let item = match item.get_mut("version") {
+                Some(version) => version,
+                None => item,
+            };
+            if let Some(requirement) = item.as_str().and_then(|r| bump_requirement(r, new_ver)) {
+                set_toml_string(item, &requirement);
+            }
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, dependencies: &[&str]) -> WorkspacePackage {
+        WorkspacePackage {
+            name: name.to_string(),
+            manifest: Some(Manifest {
+                path: format!("packages/{}/package.json", name),
+                kind: ManifestKind::Npm,
+                name: format!("@scope/{}", name),
+                dependencies: dependencies
+                    .iter()
+                    .map(|dep| format!("@scope/{}", dep))
+                    .collect(),
+            }),
+        }
+    }
+
+    fn names(packages: Vec<&WorkspacePackage>) -> Vec<&str> {
+        packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_release_order() {
+        let workspace = Workspace {
+            packages: vec![
+                package("app", &["ui", "utils"]),
+                package("ui", &["utils", "react"]),
+                package("utils", &[]),
+                package("docs", &[]),
+            ],
+        };
+
+        assert_eq!(
+            names(workspace.release_order(&["utils"], true).unwrap()),
+            vec!["utils", "ui", "app"]
+        );
+        assert_eq!(
+            names(workspace.release_order(&["app", "utils"], false).unwrap()),
+            vec!["utils", "app"]
+        );
+        assert_eq!(
+            names(workspace.release_order(&["docs"], true).unwrap()),
+            vec!["docs"]
+        );
+    }
+
+    #[test]
+    fn test_release_order_circular() {
+        let workspace = Workspace {
+            packages: vec![
+                package("a", &["b"]),
+                package("b", &["a"]),
+                package("c", &[]),
+            ],
+        };
+
+        assert_eq!(
+            names(workspace.release_order(&["c"], true).unwrap()),
+            vec!["c"]
+        );
+        assert!(workspace.release_order(&["a"], true).is_err());
+    }
+
+    #[test]
+    fn test_bump_requirement() {
+        let new_ver = Version::parse("1.4.0").unwrap();
+
+        assert_eq!(bump_requirement("^1.3.0", &new_ver).unwrap(), "^1.4.0");
+        assert_eq!(bump_requirement("~1.3", &new_ver).unwrap(), "~1.4.0");
+        assert_eq!(bump_requirement("=1.3.0-0", &new_ver).unwrap(), "=1.4.0");
+        assert_eq!(
+            bump_requirement("workspace:^1.3.0", &new_ver).unwrap(),
+            "workspace:^1.4.0"
+        );
+        assert_eq!(bump_requirement("workspace:*", &new_ver), None);
+        assert_eq!(bump_requirement("file:../utils", &new_ver), None);
+        assert_eq!(bump_requirement(">=1.0.0 <2.0.0", &new_ver), None);
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = Manifest::parse(
+            "Cargo.toml",
+            ManifestKind::Cargo,
+            r#"[package]
+name = "app"
+
+[dependencies]
+utils = { path = "../utils", version = "0.3.0" }
+ui-kit = { package = "ui", version = "1" }
+
+[dev-dependencies]
+testkit = "0.1"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name, "app");
+        assert_eq!(manifest.dependencies, vec!["utils", "ui", "testkit"]);
+        assert!(Manifest::parse("package.json", ManifestKind::Npm, "{}").is_err());
+    }
+
+    #[test]
+    fn test_update_cargo_dependency() {
+        let contents = r#"[package]
+name = "app"
+
+[dependencies]
+utils = { path = "../utils", version = "0.3.0" } # internal
+serde = "1.0"
+
+[build-dependencies]
+utils = "=0.3.0"
+"#;
+
+        let res =
+            update_cargo_dependency(contents, "utils", &Version::parse("0.4.0").unwrap()).unwrap();
+        assert_eq!(
+            res,
+            contents
+                .replace("\"0.3.0\" }", "\"0.4.0\" }")
+                .replace("\"=0.3.0\"", "\"=0.4.0\"")
+        );
+    }
+
+    #[test]
+    fn test_update_npm_dependency() {
+        let contents = r#"{
+	"name": "@scope/app",
+	"dependencies": { "@scope/utils": "^0.3.0", "left-pad": "^0.3.0" },
+	"devDependencies": { "@scope/utils": "workspace:*" }
+}
+"#;
+
+        let res =
+            update_npm_dependency(contents, "@scope/utils", &Version::parse("0.4.0").unwrap())
+                .unwrap();
+        assert_eq!(
+            res,
+            contents.replace(
+                "\"@scope/utils\": \"^0.3.0\"",
+                "\"@scope/utils\": \"^0.4.0\""
+            )
+        );
+    }
+}
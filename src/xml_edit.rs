@@ -0,0 +1,213 @@
+use eyre::Result;
+use std::ops::Range;
+
+/// Reads the trimmed text of the first element found at `path`.
+/// Returns `None` when no such element exists.
+pub fn read_text(contents: &str, path: &[&str]) -> Result<Option<String>> {
+    match find_text(contents, path)? {
+        Some(span) => Ok(Some(contents[span].to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Replaces the text of the first element found at `path`, leaving every
+/// other byte of the document untouched.
+pub fn replace_text(contents: &str, path: &[&str], new_text: &str) -> Result<String> {
+    let span = match find_text(contents, path)? {
+        Some(span) => span,
+        None => return Err(eyre!("No <{}> element found", path.join("/"))),
+    };
+
+    let mut updated_contents = contents.to_owned();
+    updated_contents.replace_range(span, new_text);
+
+    Ok(updated_contents)
+}
+
+/// Inserts `<name>text</name>` on its own line after the first element found
+/// at `path`, using the same indentation as that element.
+pub fn insert_after(contents: &str, path: &[&str], name: &str, text: &str) -> Result<String> {
+    let span = match find_text(contents, path)? {
+        Some(span) => span,
+        None => return Err(eyre!("No <{}> element found", path.join("/"))),
+    };
+
+    let element_start = contents[..span.start].rfind('<').unwrap_or(0);
+    let line_start = contents[..element_start].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &contents[line_start..element_start];
+    let indent = if indent.trim().is_empty() { indent } else { "" };
+    let element_end = match contents[span.end..].find('>') {
+        Some(i) => span.end + i + 1,
+        None => return Err(eyre!("Invalid XML, unclosed <{}>", path.join("/"))),
+    };
+
+    let mut updated_contents = contents.to_owned();
+    updated_contents.insert_str(
+        element_end,
+        &format!("\n{}<{}>{}</{}>", indent, name, text, name),
+    );
+
+    Ok(updated_contents)
+}
+
+/// Returns the byte range of the trimmed text content of the first element
+/// found at `path`, e.g. `["project", "version"]` for `/project/version`.
+pub fn find_text(contents: &str, path: &[&str]) -> Result<Option<Range<usize>>> {
+    let mut stack: Vec<&str> = vec![];
+    let mut content_start = None;
+    let mut pos = 0;
+
+    while let Some(offset) = contents[pos..].find('<') {
+        pos += offset;
+        let rest = &contents[pos..];
+
+        if rest.starts_with("<!--") {
+            pos += skip_past(rest, "-->")?;
+        } else if rest.starts_with("<![CDATA[") {
+            pos += skip_past(rest, "]]>")?;
+        } else if rest.starts_with("<?") {
+            pos += skip_past(rest, "?>")?;
+        } else if rest.starts_with("<!") {
+            pos += skip_past(rest, ">")?;
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let name = element_name(closing);
+            if stack.pop() != Some(name) {
+                return Err(eyre!("Invalid XML, unexpected </{}>", name));
+            }
+            if let Some(start) = content_start {
+                if stack.len() + 1 == path.len() {
+                    return trimmed_text(contents, start..pos).map(Some);
+                }
+            }
+            pos += skip_past(rest, ">")?;
+        } else {
+            let name = element_name(&rest[1..]);
+            let tag_len = skip_tag(rest)?;
+            pos += tag_len;
+
+            if !rest[..tag_len].ends_with("/>") {
+                stack.push(name);
+                if content_start.is_none() && stack == path {
+                    content_start = Some(pos);
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn trimmed_text(contents: &str, span: Range<usize>) -> Result<Range<usize>> {
+    let text = &contents[span.to_owned()];
+    if text.contains('<') {
+        return Err(eyre!("Expected text content, got {}", text));
+    }
+
+    let start = span.start + (text.len() - text.trim_start().len());
+    let end = span.end - (text.len() - text.trim_end().len());
+    Ok(start..end.max(start))
+}
+
+fn element_name(tag: &str) -> &str {
+    let end = tag
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+/// Returns the length of an opening tag, skipping over quoted attribute values.
+fn skip_tag(tag: &str) -> Result<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Ok(i + 1),
+            _ => {}
+        }
+    }
+    Err(eyre!("Invalid XML, unclosed tag"))
+}
+
+fn skip_past(rest: &str, end: &str) -> Result<usize> {
+    rest.find(end)
+        .map(|i| i + end.len())
+        .ok_or_else(|| eyre!("Invalid XML, missing {}", end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static POM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <!-- <version>0.0.0</version> -->
+  <parent>
+    <groupId>com.example</groupId>
+    <version>2.0.0</version>
+  </parent>
+  <artifactId name="a>b">service</artifactId>
+  <version>
+    1.4.0-SNAPSHOT
+  </version>
+  <dependencies>
+    <dependency>
+      <version>3.1.0</version>
+    </dependency>
+  </dependencies>
+</project>
+"#;
+
+    #[test]
+    fn test_read_text() {
+        assert_eq!(
+            read_text(POM, &["project", "version"]).unwrap(),
+            Some("1.4.0-SNAPSHOT".to_string())
+        );
+        assert_eq!(
+            read_text(POM, &["project", "parent", "version"]).unwrap(),
+            Some("2.0.0".to_string())
+        );
+        assert_eq!(read_text(POM, &["project", "name"]).unwrap(), None);
+        assert!(read_text(POM, &["project", "parent"]).is_err());
+    }
+
+    #[test]
+    fn test_replace_text() {
+        let res = replace_text(POM, &["project", "version"], "1.4.0").unwrap();
+        assert_eq!(res, POM.replace("    1.4.0-SNAPSHOT\n", "    1.4.0\n"));
+
+        assert!(replace_text(POM, &["project", "name"], "1.4.0").is_err());
+    }
+
+    #[test]
+    fn test_insert_after() {
+        let contents = r#"<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <VersionPrefix>1.0.0</VersionPrefix>
+  </PropertyGroup>
+</Project>
+"#;
+
+        let res = insert_after(
+            contents,
+            &["Project", "PropertyGroup", "VersionPrefix"],
+            "VersionSuffix",
+            "beta",
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            contents.replace(
+                "</VersionPrefix>",
+                "</VersionPrefix>\n    <VersionSuffix>beta</VersionSuffix>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_text_invalid() {
+        assert!(find_text("<project><version>1.0.0</project>", &["project", "version"]).is_err());
+        assert!(find_text("<project attr=\"1", &["project", "version"]).is_err());
+    }
+}